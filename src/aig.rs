@@ -0,0 +1,287 @@
+mod aiger;
+mod blast;
+mod blif;
+
+use std::collections::HashMap;
+
+/// AIGの辺。変数番号の2倍に否定のビットを足したもの(AIGERと同じ)。
+/// 変数0は定数の偽。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub const FALSE: Lit = Lit(0);
+    pub const TRUE: Lit = Lit(1);
+
+    pub fn new(var: u32, neg: bool) -> Self {
+        Lit(var * 2 + neg as u32)
+    }
+
+    pub fn from_code(code: u32) -> Self {
+        Lit(code)
+    }
+
+    pub fn code(self) -> u32 {
+        self.0
+    }
+
+    pub fn var(self) -> u32 {
+        self.0 / 2
+    }
+
+    pub fn is_neg(self) -> bool {
+        self.0 & 1 == 1
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AigNode {
+    Const,
+    Input,
+    Latch { next: Lit, init: bool },
+    And(Lit, Lit),
+}
+
+/// ラッチ付きのAND-インバータグラフ。
+/// ANDは作るときに構造ハッシュで同じものをまとめ、入力は常に自分より前の変数を指す。
+#[derive(Debug, Clone)]
+pub struct Aig {
+    nodes: Vec<AigNode>,
+    inputs: Vec<(u32, String)>,
+    latches: Vec<(u32, String)>,
+    outputs: Vec<(Lit, String)>,
+    strash: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aig {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![AigNode::Const],
+            inputs: Vec::new(),
+            latches: Vec::new(),
+            outputs: Vec::new(),
+            strash: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, node: AigNode) -> u32 {
+        self.nodes.push(node);
+        (self.nodes.len() - 1) as u32
+    }
+
+    pub fn add_input(&mut self, name: String) -> Lit {
+        let var = self.push(AigNode::Input);
+        self.inputs.push((var, name));
+        Lit::new(var, false)
+    }
+
+    /// 次の値は後から`set_next`で決める
+    pub fn add_latch(&mut self, name: String, init: bool) -> Lit {
+        let var = self.push(AigNode::Latch {
+            next: Lit::FALSE,
+            init,
+        });
+        self.latches.push((var, name));
+        Lit::new(var, false)
+    }
+
+    pub fn set_next(&mut self, latch: Lit, next: Lit) {
+        match &mut self.nodes[latch.var() as usize] {
+            AigNode::Latch { next: n, .. } => *n = next,
+            _ => panic!("not a latch"),
+        }
+    }
+
+    pub fn add_output(&mut self, name: String, lit: Lit) {
+        self.outputs.push((lit, name));
+    }
+
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        if a == Lit::FALSE || b == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if a == Lit::TRUE || a == b {
+            return b;
+        }
+        if b == Lit::TRUE {
+            return a;
+        }
+        let key = if a < b { (a, b) } else { (b, a) };
+        if let Some(&l) = self.strash.get(&key) {
+            return l;
+        }
+        let l = Lit::new(self.push(AigNode::And(key.0, key.1)), false);
+        self.strash.insert(key, l);
+        l
+    }
+
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let both = self.and(a, b);
+        let neither = self.and(!a, !b);
+        self.and(!both, !neither)
+    }
+
+    /// `s`が真なら`t`、偽なら`e`
+    pub fn mux(&mut self, s: Lit, t: Lit, e: Lit) -> Lit {
+        let t = self.and(s, t);
+        let e = self.and(!s, e);
+        self.or(t, e)
+    }
+
+    pub fn node(&self, var: u32) -> AigNode {
+        self.nodes[var as usize]
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn inputs(&self) -> &[(u32, String)] {
+        &self.inputs
+    }
+
+    pub fn latches(&self) -> &[(u32, String)] {
+        &self.latches
+    }
+
+    pub fn outputs(&self) -> &[(Lit, String)] {
+        &self.outputs
+    }
+
+    pub fn num_ands(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n, AigNode::And(..)))
+            .count()
+    }
+
+    /// 入力とラッチからの最大のANDの段数
+    pub fn depth(&self) -> usize {
+        let mut level = vec![0; self.nodes.len()];
+        for (i, n) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = n {
+                level[i] = 1 + std::cmp::max(level[a.var() as usize], level[b.var() as usize]);
+            }
+        }
+        let roots = self
+            .outputs
+            .iter()
+            .map(|o| o.0)
+            .chain(self.latches.iter().map(|&(v, _)| match self.node(v) {
+                AigNode::Latch { next, .. } => next,
+                _ => Lit::FALSE,
+            }));
+        roots.map(|l| level[l.var() as usize]).max().unwrap_or(0)
+    }
+
+    /// 出力とラッチから届かないANDを取り除き、入力、ラッチ、ANDの順に番号を振り直す
+    pub fn cleanup(&self) -> Aig {
+        let mut live = vec![false; self.nodes.len()];
+        let mut stack: Vec<u32> = self.outputs.iter().map(|o| o.0.var()).collect();
+        stack.extend(self.latches.iter().map(|l| l.0));
+        while let Some(v) = stack.pop() {
+            if std::mem::replace(&mut live[v as usize], true) {
+                continue;
+            }
+            match self.node(v) {
+                AigNode::And(a, b) => stack.extend([a.var(), b.var()]),
+                AigNode::Latch { next, .. } => stack.push(next.var()),
+                _ => {}
+            }
+        }
+
+        let mut dst = Aig::new();
+        let mut map = vec![Lit::FALSE; self.nodes.len()];
+        let tr = |map: &[Lit], l: Lit| {
+            if l.is_neg() {
+                !map[l.var() as usize]
+            } else {
+                map[l.var() as usize]
+            }
+        };
+        for (v, name) in &self.inputs {
+            map[*v as usize] = dst.add_input(name.clone());
+        }
+        for (v, name) in &self.latches {
+            let init = matches!(self.node(*v), AigNode::Latch { init: true, .. });
+            map[*v as usize] = dst.add_latch(name.clone(), init);
+        }
+        for (v, n) in self.nodes.iter().enumerate() {
+            if let (AigNode::And(a, b), true) = (n, live[v]) {
+                map[v] = dst.and(tr(&map, *a), tr(&map, *b));
+            }
+        }
+        for (v, _) in &self.latches {
+            if let AigNode::Latch { next, .. } = self.node(*v) {
+                dst.set_next(map[*v as usize], tr(&map, next));
+            }
+        }
+        for (l, name) in &self.outputs {
+            dst.add_output(name.clone(), tr(&map, *l));
+        }
+        dst
+    }
+
+    /// 現在のラッチの値`state`と入力で出力を計算し、`state`を次の値に進める
+    pub fn step(&self, inputs: &[bool], state: &mut [bool]) -> Vec<bool> {
+        let mut val = vec![false; self.nodes.len()];
+        for ((v, _), &x) in self.inputs.iter().zip(inputs) {
+            val[*v as usize] = x;
+        }
+        for ((v, _), &x) in self.latches.iter().zip(state.iter()) {
+            val[*v as usize] = x;
+        }
+        let get = |val: &[bool], l: Lit| val[l.var() as usize] ^ l.is_neg();
+        for (v, n) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = n {
+                val[v] = get(&val, *a) && get(&val, *b);
+            }
+        }
+        for ((v, _), s) in self.latches.iter().zip(state.iter_mut()) {
+            if let AigNode::Latch { next, .. } = self.node(*v) {
+                *s = get(&val, next);
+            }
+        }
+        self.outputs.iter().map(|o| get(&val, o.0)).collect()
+    }
+
+    /// ラッチの初期値
+    pub fn init_state(&self) -> Vec<bool> {
+        self.latches
+            .iter()
+            .map(|&(v, _)| matches!(self.node(v), AigNode::Latch { init: true, .. }))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Aig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "aig: {} inputs, {} latches, {} outputs, {} ands, depth {}",
+            self.inputs.len(),
+            self.latches.len(),
+            self.outputs.len(),
+            self.num_ands(),
+            self.depth()
+        )
+    }
+}
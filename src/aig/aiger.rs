@@ -0,0 +1,318 @@
+use super::{Aig, AigNode, Lit};
+use crate::error::{Error, ErrorKind};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+impl Aig {
+    /// 入力、ラッチ、ANDの順に番号が振られ、ANDの入力が自分より前を指しているか
+    fn is_canonical(&self) -> bool {
+        let (i, l) = (self.inputs.len(), self.latches.len());
+        self.inputs
+            .iter()
+            .enumerate()
+            .all(|(k, v)| v.0 as usize == k + 1)
+            && self
+                .latches
+                .iter()
+                .enumerate()
+                .all(|(k, v)| v.0 as usize == i + k + 1)
+            && self.nodes[i + l + 1..]
+                .iter()
+                .all(|n| matches!(n, AigNode::And(..)))
+    }
+
+    fn canonical(&self) -> Cow<'_, Aig> {
+        if self.is_canonical() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.cleanup())
+        }
+    }
+
+    fn header(&self, format: &str) -> String {
+        format!(
+            "{} {} {} {} {} {}\n",
+            format,
+            self.num_vars(),
+            self.inputs.len(),
+            self.latches.len(),
+            self.outputs.len(),
+            self.num_ands()
+        )
+    }
+
+    fn latch_line(&self, var: u32) -> String {
+        match self.node(var) {
+            AigNode::Latch { next, init } => format!("{} {}", next.code(), init as u32),
+            _ => unreachable!(),
+        }
+    }
+
+    fn symbols(&self) -> String {
+        let mut s = String::new();
+        for (k, (_, name)) in self.inputs.iter().enumerate() {
+            s += &format!("i{} {}\n", k, name);
+        }
+        for (k, (_, name)) in self.latches.iter().enumerate() {
+            s += &format!("l{} {}\n", k, name);
+        }
+        for (k, (_, name)) in self.outputs.iter().enumerate() {
+            s += &format!("o{} {}\n", k, name);
+        }
+        s
+    }
+
+    /// ASCII形式のAIGER(`aag`)
+    pub fn to_aag(&self) -> String {
+        let aig = self.canonical();
+        let mut s = aig.header("aag");
+        for &(v, _) in &aig.inputs {
+            s += &format!("{}\n", v * 2);
+        }
+        for &(v, _) in &aig.latches {
+            s += &format!("{} {}\n", v * 2, aig.latch_line(v));
+        }
+        for (l, _) in &aig.outputs {
+            s += &format!("{}\n", l.code());
+        }
+        for (v, n) in aig.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = n {
+                s += &format!("{} {} {}\n", v * 2, b.code(), a.code());
+            }
+        }
+        s + &aig.symbols()
+    }
+
+    /// バイナリ形式のAIGER(`aig`)
+    pub fn to_aig(&self) -> Vec<u8> {
+        let aig = self.canonical();
+        let mut out = aig.header("aig").into_bytes();
+        for &(v, _) in &aig.latches {
+            out.extend(format!("{}\n", aig.latch_line(v)).bytes());
+        }
+        for (l, _) in &aig.outputs {
+            out.extend(format!("{}\n", l.code()).bytes());
+        }
+        for (v, n) in aig.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = n {
+                // 入力は大きい方から差分で書く
+                let lhs = v as u32 * 2;
+                encode(&mut out, lhs - b.code());
+                encode(&mut out, b.code() - a.code());
+            }
+        }
+        out.extend(aig.symbols().bytes());
+        out
+    }
+
+    /// ASCIIとバイナリのどちらのAIGERも読む。
+    /// 初期値が不定のラッチは0で始まるものとして扱う。
+    pub fn from_aiger(data: &[u8]) -> Result<Aig, Error> {
+        let mut r = Reader { data, pos: 0 };
+        let header = r.line()?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let binary = match fields.first() {
+            Some(&"aag") => false,
+            Some(&"aig") => true,
+            _ => return Err(syntax("not an AIGER file")),
+        };
+        let nums: Vec<u32> = fields[1..]
+            .iter()
+            .map(|f| f.parse().map_err(|_| syntax("bad header")))
+            .collect::<Result<_, _>>()?;
+        if nums.len() < 5 || nums[5..].iter().any(|&n| n != 0) {
+            return Err(syntax("only M I L O A headers are supported"));
+        }
+        let (m, i, l, o, a) = (nums[0], nums[1], nums[2], nums[3], nums[4]);
+        // 変数の番号はリテラルにすると`u32`に収まる
+        if m > u32::MAX / 2 || i as u64 + l as u64 + a as u64 > m as u64 {
+            return Err(syntax("bad header"));
+        }
+
+        let mut inputs = Vec::new();
+        for k in 0..i {
+            inputs.push(if binary { (k + 1) * 2 } else { r.fields(1)?[0] });
+        }
+        let mut latches = Vec::new();
+        for k in 0..l {
+            let mut n = r.fields(if binary { 1 } else { 2 })?;
+            if binary {
+                n.insert(0, (i + k + 1) * 2);
+            }
+            let init = n.get(2).copied().unwrap_or(0);
+            latches.push((n[0], n[1], init == 1));
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..o {
+            outputs.push(r.fields(1)?[0]);
+        }
+        let mut ands = Vec::new();
+        for k in 0..a {
+            if binary {
+                let lhs = (i + l + k + 1) * 2;
+                let (d0, d1) = (r.decode()?, r.decode()?);
+                let r0 = lhs.checked_sub(d0);
+                let r1 = r0.and_then(|r0| r0.checked_sub(d1));
+                let (Some(r0), Some(r1)) = (r0, r1) else {
+                    return Err(syntax("bad AND delta"));
+                };
+                ands.push((lhs, r0, r1));
+            } else {
+                let n = r.fields(3)?;
+                ands.push((n[0], n[1], n[2]));
+            }
+        }
+
+        let mut names = HashMap::new();
+        while r.pos < r.data.len() {
+            let line = r.line()?;
+            if line == "c" {
+                break;
+            }
+            if let Some((key, name)) = line.split_once(' ') {
+                names.insert(key.to_string(), name.to_string());
+            }
+        }
+        let name = |key: String| names.get(&key).cloned().unwrap_or(key);
+
+        let mut aig = Aig::new();
+        let mut map: HashMap<u32, Lit> = HashMap::new();
+        map.insert(0, Lit::FALSE);
+        for (k, &lit) in inputs.iter().enumerate() {
+            map.insert(lit / 2, aig.add_input(name(format!("i{}", k))));
+        }
+        for (k, &(lit, _, init)) in latches.iter().enumerate() {
+            map.insert(lit / 2, aig.add_latch(name(format!("l{}", k)), init));
+        }
+        let defs: HashMap<u32, (u32, u32)> = ands
+            .iter()
+            .map(|&(lhs, r0, r1)| (lhs / 2, (r0, r1)))
+            .collect();
+        for &(lhs, _, _) in &ands {
+            resolve(&mut aig, &defs, &mut map, lhs / 2)?;
+        }
+        let tr = |map: &HashMap<u32, Lit>, code: u32| -> Result<Lit, Error> {
+            let l = *map
+                .get(&(code / 2))
+                .ok_or_else(|| syntax("undefined literal"))?;
+            Ok(if code & 1 == 1 { !l } else { l })
+        };
+        for &(lit, next, _) in &latches {
+            let next = tr(&map, next)?;
+            aig.set_next(map[&(lit / 2)], next);
+        }
+        for (k, &lit) in outputs.iter().enumerate() {
+            let l = tr(&map, lit)?;
+            aig.add_output(name(format!("o{}", k)), l);
+        }
+        Ok(aig)
+    }
+}
+
+/// ANDの変数`var`を、入力から先に作りながら`aig`に加える。
+/// 深さ優先でたどり、入力を積んだまま終わっていない変数にまた着いたらループ。
+fn resolve(
+    aig: &mut Aig,
+    defs: &HashMap<u32, (u32, u32)>,
+    map: &mut HashMap<u32, Lit>,
+    var: u32,
+) -> Result<(), Error> {
+    let mut visiting = HashSet::new();
+    let mut stack = vec![var];
+    while let Some(&v) = stack.last() {
+        if map.contains_key(&v) {
+            stack.pop();
+            continue;
+        }
+        let &(r0, r1) = defs.get(&v).ok_or_else(|| syntax("undefined literal"))?;
+        if visiting.insert(v) {
+            for x in [r0 / 2, r1 / 2] {
+                if map.contains_key(&x) {
+                    continue;
+                }
+                if visiting.contains(&x) {
+                    return Err(syntax("cyclic AND definitions"));
+                }
+                stack.push(x);
+            }
+            continue;
+        }
+        let get = |c: u32| {
+            if c & 1 == 1 {
+                !map[&(c / 2)]
+            } else {
+                map[&(c / 2)]
+            }
+        };
+        let l = aig.and(get(r0), get(r1));
+        map.insert(v, l);
+        visiting.remove(&v);
+        stack.pop();
+    }
+    Ok(())
+}
+
+fn encode(out: &mut Vec<u8>, mut x: u32) {
+    while x & !0x7f != 0 {
+        out.push((x & 0x7f) as u8 | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+fn syntax(msg: &str) -> Error {
+    Error::new(ErrorKind::SyntaxError, msg.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn line(&mut self) -> Result<String, Error> {
+        if self.pos >= self.data.len() {
+            return Err(syntax("unexpected end of file"));
+        }
+        let rest = &self.data[self.pos..];
+        let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        self.pos += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    fn numbers(&mut self) -> Result<Vec<u32>, Error> {
+        self.line()?
+            .split_whitespace()
+            .map(|f| f.parse().map_err(|_| syntax("expected a number")))
+            .collect()
+    }
+
+    /// 少なくとも`n`個の数が並ぶ行
+    fn fields(&mut self, n: usize) -> Result<Vec<u32>, Error> {
+        let nums = self.numbers()?;
+        if nums.len() < n {
+            return Err(syntax(&format!("expected {} numbers", n)));
+        }
+        Ok(nums)
+    }
+
+    fn decode(&mut self) -> Result<u32, Error> {
+        let mut x = 0u32;
+        let mut shift = 0;
+        loop {
+            let b = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| syntax("unexpected end of file"))?;
+            self.pos += 1;
+            x |= ((b & 0x7f) as u32) << shift;
+            if b & 0x80 == 0 {
+                return Ok(x);
+            }
+            shift += 7;
+            if shift >= 32 {
+                return Err(syntax("delta too large"));
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Aig, Lit};
+use crate::error::{Error, ErrorKind};
+use crate::netlist::{CellKind, NetId, Netlist};
+use std::collections::HashMap;
+
+/// ワードは下位ビットから並べる
+type Word = Vec<Lit>;
+
+impl Aig {
+    /// ネットリストをビット単位に展開する。
+    /// 入力、レジスタ、出力の各ビットには`name[i]`の名前を付ける。
+    /// レジスタを通らないループがあるとエラー。
+    pub fn from_netlist(nl: &Netlist) -> Result<Aig, Error> {
+        let mut aig = Aig::new();
+        let mut words: HashMap<NetId, Word> = HashMap::new();
+        for &i in nl.inputs() {
+            let net = nl.net(i);
+            let name = net.name.map(|s| s.to_string()).unwrap_or_default();
+            let w = (0..net.width)
+                .map(|b| aig.add_input(format!("{}[{}]", name, b)))
+                .collect();
+            words.insert(i, w);
+        }
+        let regs: Vec<_> = nl.registers().map(|(_, c)| c.clone()).collect();
+        for r in &regs {
+            let net = nl.net(r.output);
+            let name = net.name.map(|s| s.to_string()).unwrap_or_default();
+            let init = match r.kind {
+                CellKind::Dff { init } => init,
+                _ => 0,
+            };
+            let w = (0..net.width)
+                .map(|b| aig.add_latch(format!("{}[{}]", name, b), (init >> b) & 1 == 1))
+                .collect();
+            words.insert(r.output, w);
+        }
+        let order = nl
+            .topo_order()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "combinational loop in netlist"))?;
+        for id in order {
+            let cell = nl.cell(id);
+            let args: Vec<&Word> = cell.inputs.iter().map(|n| &words[n]).collect();
+            let out = aig.blast(cell.kind, &args, nl.net(cell.output).width);
+            words.insert(cell.output, out);
+        }
+        for r in &regs {
+            let next = words[&r.inputs[0]].clone();
+            for (&q, d) in words[&r.output].iter().zip(next) {
+                aig.set_next(q, d);
+            }
+        }
+        for &(name, net) in nl.outputs() {
+            for (b, &l) in words[&net].iter().enumerate() {
+                aig.add_output(format!("{}[{}]", name, b), l);
+            }
+        }
+        Ok(aig.cleanup())
+    }
+
+    fn blast(&mut self, kind: CellKind, args: &[&Word], width: u32) -> Word {
+        match kind {
+            CellKind::Const { value, .. } => (0..width)
+                .map(|b| {
+                    if b < 64 && (value >> b) & 1 == 1 {
+                        Lit::TRUE
+                    } else {
+                        Lit::FALSE
+                    }
+                })
+                .collect(),
+            CellKind::Add => self.add(args[0], args[1], Lit::FALSE).0,
+            CellKind::Sub => self.sub(args[0], args[1]).0,
+            CellKind::Mul => self.mul(args[0], args[1]),
+            CellKind::Div => self.sdiv(args[0], args[1]),
+            CellKind::Neg => self.neg(args[0]),
+            CellKind::Eq => vec![self.eq(args[0], args[1])],
+            CellKind::Ne => vec![!self.eq(args[0], args[1])],
+            CellKind::Gt => vec![self.slt(args[1], args[0])],
+            CellKind::Ge => vec![!self.slt(args[0], args[1])],
+            CellKind::Concat => args.iter().rev().flat_map(|w| w.iter().copied()).collect(),
+            CellKind::Dff { .. } => unreachable!("registers are not combinational"),
+        }
+    }
+
+    /// 繰り上がり付きの加算。和と最上位からの桁上げを返す。
+    fn add(&mut self, a: &[Lit], b: &[Lit], mut carry: Lit) -> (Word, Lit) {
+        let mut sum = Vec::with_capacity(a.len());
+        for (&x, &y) in a.iter().zip(b) {
+            let p = self.xor(x, y);
+            sum.push(self.xor(p, carry));
+            let g = self.and(x, y);
+            let c = self.and(p, carry);
+            carry = self.or(g, c);
+        }
+        (sum, carry)
+    }
+
+    /// `a - b`と、借りがないとき(符号なしで`a >= b`)に真になる桁上げ
+    fn sub(&mut self, a: &[Lit], b: &[Lit]) -> (Word, Lit) {
+        let nb: Word = b.iter().map(|&l| !l).collect();
+        self.add(a, &nb, Lit::TRUE)
+    }
+
+    fn neg(&mut self, a: &[Lit]) -> Word {
+        let zero = vec![Lit::FALSE; a.len()];
+        self.sub(&zero, a).0
+    }
+
+    fn mul(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        let w = a.len();
+        let mut acc = vec![Lit::FALSE; w];
+        for (i, &bi) in b.iter().enumerate() {
+            let mut pp = vec![Lit::FALSE; w];
+            for j in i..w {
+                pp[j] = self.and(a[j - i], bi);
+            }
+            acc = self.add(&acc, &pp, Lit::FALSE).0;
+        }
+        acc
+    }
+
+    /// 符号なしの引き戻し法による除算
+    fn udiv(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        let w = a.len();
+        let mut q = vec![Lit::FALSE; w];
+        // 剰余は1ビット広く持つ
+        let mut r = vec![Lit::FALSE; w + 1];
+        let mut d = b.to_vec();
+        d.push(Lit::FALSE);
+        for i in (0..w).rev() {
+            r.pop();
+            r.insert(0, a[i]);
+            let (diff, ge) = self.sub(&r, &d);
+            q[i] = ge;
+            r = r
+                .iter()
+                .zip(&diff)
+                .map(|(&keep, &sub)| self.mux(ge, sub, keep))
+                .collect();
+        }
+        q
+    }
+
+    /// 符号付き除算。0で割ったときはすべて1。
+    fn sdiv(&mut self, a: &[Lit], b: &[Lit]) -> Word {
+        let w = a.len();
+        let (sa, sb) = (a[w - 1], b[w - 1]);
+        let na = self.neg(a);
+        let nb = self.neg(b);
+        let abs_a = self.mux_word(sa, &na, a);
+        let abs_b = self.mux_word(sb, &nb, b);
+        let q = self.udiv(&abs_a, &abs_b);
+        let nq = self.neg(&q);
+        let sq = self.xor(sa, sb);
+        let res = self.mux_word(sq, &nq, &q);
+        let ones = vec![Lit::TRUE; w];
+        let zero = vec![Lit::FALSE; w];
+        let by_zero = self.eq(b, &zero);
+        self.mux_word(by_zero, &ones, &res)
+    }
+
+    fn eq(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let mut acc = Lit::TRUE;
+        for (&x, &y) in a.iter().zip(b) {
+            let d = self.xor(x, y);
+            acc = self.and(acc, !d);
+        }
+        acc
+    }
+
+    /// 符号付きの`a < b`
+    fn slt(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let w = a.len();
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a[w - 1] = !a[w - 1];
+        b[w - 1] = !b[w - 1];
+        !self.sub(&a, &b).1
+    }
+
+    fn mux_word(&mut self, s: Lit, t: &[Lit], e: &[Lit]) -> Word {
+        t.iter().zip(e).map(|(&x, &y)| self.mux(s, x, y)).collect()
+    }
+}
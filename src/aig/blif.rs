@@ -0,0 +1,257 @@
+use super::{Aig, AigNode, Lit};
+use crate::error::{Error, ErrorKind};
+use std::collections::{HashMap, HashSet};
+
+/// `.names`の入力と被覆の行(キューブと出力の値)
+type Names = (Vec<String>, Vec<(String, char)>);
+
+/// BLIFを書くときの信号名と補助の`.names`
+struct Writer<'a> {
+    aig: &'a Aig,
+    body: String,
+    defined: HashSet<String>,
+}
+
+impl Writer<'_> {
+    fn var_name(&self, var: u32) -> String {
+        match self.aig.node(var) {
+            AigNode::Input => self
+                .aig
+                .inputs
+                .iter()
+                .find(|i| i.0 == var)
+                .unwrap()
+                .1
+                .clone(),
+            AigNode::Latch { .. } => self
+                .aig
+                .latches
+                .iter()
+                .find(|l| l.0 == var)
+                .unwrap()
+                .1
+                .clone(),
+            AigNode::And(..) => format!("n{}", var),
+            AigNode::Const => "$false".to_string(),
+        }
+    }
+
+    /// `lit`の値を持つ信号名。否定や定数には補助の`.names`を足す。
+    fn signal(&mut self, lit: Lit) -> String {
+        let name = match (lit.var(), lit.is_neg()) {
+            (0, false) => "$false".to_string(),
+            (0, true) => "$true".to_string(),
+            (v, false) => return self.var_name(v),
+            (v, true) => format!("{}$not", self.var_name(v)),
+        };
+        if self.defined.insert(name.clone()) {
+            self.body += &match lit.var() {
+                0 if lit.is_neg() => format!(".names {}\n1\n", name),
+                0 => format!(".names {}\n", name),
+                v => format!(".names {} {}\n0 1\n", self.var_name(v), name),
+            };
+        }
+        name
+    }
+}
+
+impl Aig {
+    /// ABCが読めるBLIF。ANDは2入力の`.names`、ラッチは`.latch`になる。
+    pub fn to_blif(&self, model: &str) -> String {
+        let mut w = Writer {
+            aig: self,
+            body: String::new(),
+            defined: HashSet::new(),
+        };
+        let mut latches = String::new();
+        for &(v, ref name) in &self.latches {
+            if let AigNode::Latch { next, init } = self.node(v) {
+                let next = w.signal(next);
+                latches += &format!(".latch {} {} {}\n", next, name, init as u32);
+            }
+        }
+        for (v, n) in self.nodes.iter().enumerate() {
+            if let AigNode::And(a, b) = n {
+                let (sa, sb) = (w.var_name(a.var()), w.var_name(b.var()));
+                w.body += &format!(
+                    ".names {} {} n{}\n{}{} 1\n",
+                    sa,
+                    sb,
+                    v,
+                    !a.is_neg() as u32,
+                    !b.is_neg() as u32
+                );
+            }
+        }
+        for (lit, name) in &self.outputs {
+            if lit.var() != 0 && !lit.is_neg() && w.var_name(lit.var()) == *name {
+                continue;
+            }
+            let src = w.signal(*lit);
+            w.body += &format!(".names {} {}\n1 1\n", src, name);
+        }
+
+        let mut s = format!(".model {}\n", model);
+        s += &format!(".inputs {}\n", names(&self.inputs));
+        s += &format!(".outputs {}\n", names(&self.outputs));
+        s + &latches + &w.body + ".end\n"
+    }
+
+    /// 単一出力の`.names`と`.latch`だけからなるBLIFを読む。
+    /// 初期値が不定のラッチは0で始まるものとして扱う。
+    pub fn from_blif(src: &str) -> Result<Aig, Error> {
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        let mut latches = Vec::new();
+        let mut defs: HashMap<String, Names> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for line in lines(src) {
+            let mut fields = line.split_whitespace();
+            let Some(cmd) = fields.next() else { continue };
+            let fields: Vec<String> = fields.map(str::to_string).collect();
+            if !cmd.starts_with('.') {
+                // 直前の`.names`の被覆の行
+                let name = current
+                    .as_ref()
+                    .ok_or_else(|| syntax("cover line outside .names"))?;
+                let def = defs.get_mut(name).unwrap();
+                let (cube, value) = match fields.first() {
+                    Some(v) => (cmd.to_string(), v.chars().next().unwrap()),
+                    None if def.0.is_empty() => (String::new(), cmd.chars().next().unwrap()),
+                    None => return Err(syntax("cover line without output value")),
+                };
+                if cube.len() != def.0.len() {
+                    return Err(syntax("cover width does not match .names"));
+                }
+                def.1.push((cube, value));
+                continue;
+            }
+            current = None;
+            match cmd {
+                ".model" | ".end" => {}
+                ".inputs" => inputs.extend(fields),
+                ".outputs" => outputs.extend(fields),
+                ".latch" => {
+                    if fields.len() < 2 {
+                        return Err(syntax(".latch needs an input and an output"));
+                    }
+                    let init = fields.last().filter(|_| fields.len() > 2);
+                    latches.push((
+                        fields[0].clone(),
+                        fields[1].clone(),
+                        init.is_some_and(|i| i == "1"),
+                    ));
+                }
+                ".names" => {
+                    let mut fields = fields;
+                    let out = fields
+                        .pop()
+                        .ok_or_else(|| syntax(".names needs an output"))?;
+                    defs.insert(out.clone(), (fields, Vec::new()));
+                    current = Some(out);
+                }
+                _ => return Err(syntax(&format!("unsupported command {}", cmd))),
+            }
+        }
+
+        let mut aig = Aig::new();
+        let mut map: HashMap<String, Lit> = HashMap::new();
+        for name in inputs {
+            let l = aig.add_input(name.clone());
+            map.insert(name, l);
+        }
+        for (_, name, init) in &latches {
+            let l = aig.add_latch(name.clone(), *init);
+            map.insert(name.clone(), l);
+        }
+        for (next, name, _) in &latches {
+            let next = resolve(&mut aig, &defs, &mut map, next)?;
+            aig.set_next(map[name], next);
+        }
+        for name in outputs {
+            let l = resolve(&mut aig, &defs, &mut map, &name)?;
+            aig.add_output(name, l);
+        }
+        Ok(aig)
+    }
+}
+
+fn names<T>(list: &[(T, String)]) -> String {
+    list.iter()
+        .map(|x| x.1.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// コメントを除き、`\`で続く行をつないだ論理行
+fn lines(src: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut acc = String::new();
+    for line in src.lines() {
+        let line = line.split('#').next().unwrap();
+        match line.trim_end().strip_suffix('\\') {
+            Some(head) => acc += head,
+            None => {
+                acc += line;
+                out.push(std::mem::take(&mut acc));
+            }
+        }
+    }
+    out.push(acc);
+    out
+}
+
+/// 信号`name`を、入力側から先に作りながら`aig`に加える。
+/// 深さ優先でたどり、入力を積んだまま終わっていない信号にまた着いたらループ。
+fn resolve(
+    aig: &mut Aig,
+    defs: &HashMap<String, Names>,
+    map: &mut HashMap<String, Lit>,
+    name: &str,
+) -> Result<Lit, Error> {
+    let mut visiting = HashSet::new();
+    let mut stack = vec![name.to_string()];
+    while let Some(n) = stack.last().cloned() {
+        if map.contains_key(&n) {
+            stack.pop();
+            continue;
+        }
+        let (ins, cover) = defs
+            .get(&n)
+            .ok_or_else(|| syntax(&format!("undefined signal {}", n)))?;
+        if visiting.insert(n.clone()) {
+            for i in ins.iter().filter(|i| !map.contains_key(*i)) {
+                if visiting.contains(i) {
+                    return Err(syntax("combinational loop"));
+                }
+                stack.push(i.clone());
+            }
+            continue;
+        }
+        // 出力が0の行があれば補集合として読む
+        let offset = cover.iter().any(|c| c.1 == '0');
+        let mut sum = Lit::FALSE;
+        for (cube, _) in cover {
+            let mut prod = Lit::TRUE;
+            for (c, i) in cube.chars().zip(ins) {
+                let l = map[i];
+                prod = match c {
+                    '1' => aig.and(prod, l),
+                    '0' => aig.and(prod, !l),
+                    _ => prod,
+                };
+            }
+            sum = aig.or(sum, prod);
+        }
+        let l = if offset { !sum } else { sum };
+        map.insert(n.clone(), l);
+        visiting.remove(&n);
+        stack.pop();
+    }
+    Ok(map[name])
+}
+
+fn syntax(msg: &str) -> Error {
+    Error::new(ErrorKind::SyntaxError, msg.to_string())
+}
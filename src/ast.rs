@@ -1,8 +1,18 @@
+mod arena;
+mod dot;
 mod node;
 mod nodetype;
+mod parser;
+mod printer;
 mod program;
 mod rettype;
+mod visit;
 
+pub use arena::{Ast, NodeId};
 pub use node::Node;
 pub use nodetype::Ntype;
 pub use program::Program;
+pub use visit::{
+    fold_childs, walk_childs, walk_childs_mut, walk_fold, walk_node, walk_node_mut, Fold, Visitor,
+    VisitorMut,
+};
@@ -0,0 +1,101 @@
+use super::{Node, Ntype};
+use crate::datatype::DataUnion;
+use crate::symbol::Symbol;
+
+/// `Ast`の中のノードを指す番号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+pub(super) struct NodeData {
+    pub(super) ty: Ntype,
+    pub(super) value: DataUnion,
+    pub(super) span: (usize, usize),
+    childs: (u32, u32),
+}
+
+/// 構文木のノードをまとめて保持する。
+/// 子ノードは`childs`に連続して並べ、各ノードはその範囲だけを持つ。
+#[derive(Default)]
+pub struct Ast {
+    nodes: Vec<NodeData>,
+    childs: Vec<NodeId>,
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> Node<'_> {
+        Node::new(self, id)
+    }
+
+    pub fn push(
+        &mut self,
+        ty: Ntype,
+        value: DataUnion,
+        childs: &[NodeId],
+        span: (usize, usize),
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        let start = self.childs.len() as u32;
+        self.childs.extend_from_slice(childs);
+        self.nodes.push(NodeData {
+            ty,
+            value,
+            span,
+            childs: (start, childs.len() as u32),
+        });
+        id
+    }
+
+    pub fn number(&mut self, n: i32, span: (usize, usize)) -> NodeId {
+        self.push(Ntype::Num, DataUnion::Num(n), &[], span)
+    }
+
+    pub fn lvar(&mut self, name: Symbol, span: (usize, usize)) -> NodeId {
+        self.push(Ntype::Lvar, DataUnion::Symbol(name), &[], span)
+    }
+
+    pub fn from_childs(&mut self, ty: Ntype, childs: &[NodeId], span: (usize, usize)) -> NodeId {
+        self.push(ty, DataUnion::None, childs, span)
+    }
+
+    pub fn set_ty(&mut self, id: NodeId, ty: Ntype) {
+        self.nodes[id.index()].ty = ty;
+    }
+
+    pub fn set_value(&mut self, id: NodeId, value: DataUnion) {
+        self.nodes[id.index()].value = value;
+    }
+
+    /// `id`の`i`番目の子を`child`に差し替える
+    pub fn set_child(&mut self, id: NodeId, i: usize, child: NodeId) {
+        let (start, len) = self.nodes[id.index()].childs;
+        assert!(i < len as usize);
+        self.childs[start as usize + i] = child;
+    }
+
+    pub(super) fn data(&self, id: NodeId) -> &NodeData {
+        &self.nodes[id.index()]
+    }
+
+    pub(super) fn childs_of(&self, id: NodeId) -> &[NodeId] {
+        let (start, len) = self.data(id).childs;
+        &self.childs[start as usize..(start + len) as usize]
+    }
+}
@@ -0,0 +1,74 @@
+use super::{Node, Ntype, Program, Visitor};
+use crate::datatype::DataUnion;
+
+impl Node<'_> {
+    fn dot_label(self) -> String {
+        match (self.ty(), self.value()) {
+            (_, DataUnion::Num(n)) => n.to_string(),
+            (_, DataUnion::Symbol(s)) => s.to_string(),
+            (Ntype::Add, _) => "+".to_string(),
+            (Ntype::Sub, _) => "-".to_string(),
+            (Ntype::Mul, _) => "*".to_string(),
+            (Ntype::Div, _) => "/".to_string(),
+            (Ntype::Neg, _) => "neg".to_string(),
+            (Ntype::Eq, _) => "==".to_string(),
+            (Ntype::Neq, _) => "!=".to_string(),
+            (Ntype::Greater, _) => ">".to_string(),
+            (Ntype::GreaterEq, _) => ">=".to_string(),
+            (Ntype::Less, _) => "<".to_string(),
+            (Ntype::LessEq, _) => "<=".to_string(),
+            (Ntype::Assign, _) => "=".to_string(),
+            (ty, _) => format!("{:?}", ty),
+        }
+    }
+
+    /// この文だけの構文木をGraphvizのDOTにする
+    pub fn to_dot(self) -> String {
+        let mut s = "digraph ast {\n  ordering=out;\n".to_string();
+        self.write_dot(&mut s);
+        s + "}\n"
+    }
+
+    fn write_dot(self, out: &mut String) {
+        DotWriter { out }.visit_node(self);
+    }
+}
+
+/// 節と、親から子への辺を書き出す
+struct DotWriter<'o> {
+    out: &'o mut String,
+}
+
+impl<'a> Visitor<'a> for DotWriter<'_> {
+    fn visit_node(&mut self, node: Node<'a>) {
+        // 葉は四角、演算は楕円
+        let shape = if node.childs().len() == 0 {
+            "box"
+        } else {
+            "ellipse"
+        };
+        *self.out += &format!(
+            "  n{} [label=\"{}\", shape={}];\n",
+            node.id().index(),
+            node.dot_label(),
+            shape
+        );
+        for ch in node.childs() {
+            self.visit_node(ch);
+            *self.out += &format!("  n{} -> n{};\n", node.id().index(), ch.id().index());
+        }
+    }
+}
+
+impl Program {
+    /// 構文木をGraphvizのDOTにする。子は左から順に並ぶ。
+    pub fn to_dot(&self) -> String {
+        let mut s = "digraph ast {\n  ordering=out;\n".to_string();
+        for (i, stmt) in self.stmts().enumerate() {
+            s += &format!("  s{} [label=\"stmt {}\", shape=plaintext];\n", i, i);
+            s += &format!("  s{} -> n{};\n", i, stmt.id().index());
+            stmt.write_dot(&mut s);
+        }
+        s + "}\n"
+    }
+}
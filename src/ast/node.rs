@@ -1,281 +1,153 @@
+use super::arena::{Ast, NodeId};
 use super::rettype::RetType;
 use super::Ntype;
 use crate::datatype::DataUnion;
 use crate::error::{Error, ErrorKind};
-use crate::tokenize::{TokenGen, TokenKind};
+use crate::symbol::Symbol;
 use std::collections::HashMap;
-use std::rc::Rc;
 
-#[derive(Clone)]
-#[allow(dead_code)]
-pub struct Node {
-    ty: Ntype,
-    childs: Option<Rc<(Node, Node)>>,
-    value: DataUnion,
+/// `Ast`の中の一つのノードへの参照
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    ast: &'a Ast,
+    id: NodeId,
 }
 
-impl std::fmt::Debug for Node {
+impl std::fmt::Debug for Node<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.format_debug(f, 0)
     }
 }
 
 #[allow(dead_code)]
-impl Node {
-    pub fn new(ty: Ntype) -> Self {
-        Self {
-            ty,
-            childs: None,
-            value: match ty {
-                Ntype::Num => DataUnion::Num(0),
-                _ => DataUnion::None,
-            },
-        }
+impl<'a> Node<'a> {
+    pub(super) fn new(ast: &'a Ast, id: NodeId) -> Self {
+        Self { ast, id }
     }
 
-    pub fn number(n: i32) -> Self {
-        Self {
-            ty: Ntype::Num,
-            childs: None,
-            value: DataUnion::Num(n),
-        }
+    pub fn id(self) -> NodeId {
+        self.id
     }
 
-    pub fn lvar(name: Vec<char>) -> Self {
-        Self {
-            ty: Ntype::Lvar,
-            childs: None,
-            value: DataUnion::String(name),
-        }
+    pub fn ty(self) -> Ntype {
+        self.ast.data(self.id).ty
     }
 
-    pub fn from_child(ch1: Self, ch2: Self, ty: Ntype) -> Self {
-        Self {
-            ty,
-            childs: Some(Rc::new((ch1, ch2))),
-            value: DataUnion::None,
-        }
+    pub fn value(self) -> &'a DataUnion {
+        &self.ast.data(self.id).value
     }
 
-    pub fn gen_tree(token: &mut TokenGen) -> Result<Vec<Self>, Error> {
-        let tr = Node::program(token)?;
-        if token.current().is_kind(TokenKind::Eof) {
-            Ok(tr)
-        } else {
-            token.error_at("", None);
-            Err(Error::new(ErrorKind::InvalidData, "unexpected error."))
-        }
+    /// ソース上の位置(文字単位の半開区間)
+    pub fn span(self) -> (usize, usize) {
+        self.ast.data(self.id).span
     }
 
-    pub fn program(token: &mut TokenGen) -> Result<Vec<Self>, Error> {
-        let mut nodes = Vec::new();
-        while !token.current().is_kind(TokenKind::Eof) {
-            nodes.push(Self::stmt(token)?);
-        }
-        Ok(nodes)
+    pub fn childs(self) -> impl ExactSizeIterator<Item = Node<'a>> + 'a {
+        let ast = self.ast;
+        ast.childs_of(self.id)
+            .iter()
+            .map(move |&id| Node::new(ast, id))
     }
 
-    pub fn stmt(token: &mut TokenGen) -> Result<Self, Error> {
-        let node = Self::expr(token)?;
-        token.expect(DataUnion::char(';'))?;
-        Ok(node)
+    pub fn child(self, i: usize) -> Option<Node<'a>> {
+        self.ast
+            .childs_of(self.id)
+            .get(i)
+            .map(|&id| Node::new(self.ast, id))
     }
 
-    fn expr(token: &mut TokenGen) -> Result<Self, Error> {
-        Self::assign(token)
+    /// 代入の左辺に置ける式かどうか
+    pub fn is_lvalue(self) -> bool {
+        matches!(self.ty(), Ntype::Lvar)
     }
 
-    fn assign(token: &mut TokenGen) -> Result<Self, Error> {
-        let node = Self::equality(token)?;
-        if token.consume(DataUnion::char('='))? {
-            Ok(Node::from_child(Self::assign(token)?, node, Ntype::Assign))
-        } else {
-            Ok(node)
-        }
-    }
-
-    fn equality(token: &mut TokenGen) -> Result<Self, Error> {
-        let mut node = Self::relational(token)?;
-        loop {
-            if token.consume(DataUnion::str("=="))? {
-                node = Self::from_child(node, Self::relational(token)?, Ntype::Eq);
-            } else if token.consume(DataUnion::str("!="))? {
-                node = Self::from_child(node, Self::relational(token)?, Ntype::Neq);
-            } else {
-                break;
-            }
-        }
-        Ok(node)
-    }
-
-    fn relational(token: &mut TokenGen) -> Result<Self, Error> {
-        let mut node = Self::add(token)?;
-        loop {
-            if token.consume(DataUnion::str(">"))? {
-                node = Self::from_child(node, Self::relational(token)?, Ntype::Greater);
-            } else if token.consume(DataUnion::str("<"))? {
-                node = Self::from_child(Self::relational(token)?, node, Ntype::Greater);
-            } else if token.consume(DataUnion::str(">="))? {
-                node = Self::from_child(node, Self::relational(token)?, Ntype::GreaterEq);
-            } else if token.consume(DataUnion::str("<="))? {
-                node = Self::from_child(Self::relational(token)?, node, Ntype::GreaterEq);
-            } else {
-                break;
-            }
-        }
-        Ok(node)
-    }
-
-    fn add(token: &mut TokenGen) -> Result<Self, Error> {
-        let mut node = Node::mul(token)?;
-
-        while !token.current().is_kind(TokenKind::Eof) {
-            if token.consume(DataUnion::char('+'))? {
-                node = Node::from_child(node, Node::mul(token)?, Ntype::Add);
-            } else if token.consume(DataUnion::char('-'))? {
-                node = Node::from_child(node, Node::mul(token)?, Ntype::Sub);
-            } else {
-                return Ok(node);
-            }
-        }
-        Ok(node)
+    fn expect_child(self, i: usize) -> Result<Node<'a>, Error> {
+        self.child(i)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedError, ""))
     }
 
-    fn mul(token: &mut TokenGen) -> Result<Self, Error> {
-        let mut node = Node::unary(token)?;
-
-        while !token.current().is_kind(TokenKind::Eof) {
-            if token.consume(DataUnion::char('*'))? {
-                node = Node::from_child(node, Node::unary(token)?, Ntype::Mul);
-            } else if token.consume(DataUnion::char('/'))? {
-                node = Node::from_child(node, Node::unary(token)?, Ntype::Div);
-            } else {
-                return Ok(node);
-            }
-        }
-        Ok(node)
-    }
-
-    fn unary(token: &mut TokenGen) -> Result<Self, Error> {
-        if token.consume(DataUnion::char('+'))? {
-            Node::primary(token)
-        } else if token.consume(DataUnion::char('-'))? {
-            Ok(Node::from_child(
-                Node::number(0),
-                Node::primary(token)?,
-                Ntype::Sub,
-            ))
-        } else {
-            Node::primary(token)
-        }
-    }
-
-    fn primary(token: &mut TokenGen) -> Result<Self, Error> {
-        if token.consume(DataUnion::char('('))? {
-            let node = Node::expr(token)?;
-            token.expect(DataUnion::char(')'))?;
-            Ok(node)
-        } else if token.consume_kind(TokenKind::Number) {
-            Ok(Node::number(token.get_number()?))
-        } else {
-            Ok(Node::lvar(token.get_ident()?))
-        }
-    }
-
-    fn get_child(&self) -> Option<&(Self, Self)> {
-        if let Some(p) = &self.childs {
-            Some(&p)
-        } else {
-            None
-        }
-    }
-
-    fn expect_child(&self) -> Result<&(Self, Self), Error> {
-        if let Some(p) = &self.childs {
-            Ok(&p)
-        } else {
-            Err(Error::new(ErrorKind::UnexpectedError, ""))
-        }
+    fn binary(
+        self,
+        var: &mut HashMap<Symbol, usize>,
+        mem: &mut Vec<i32>,
+    ) -> Result<(i32, i32), Error> {
+        let l = self.expect_child(0)?.compile(var, mem)?.rvalue(mem)?;
+        let r = self.expect_child(1)?.compile(var, mem)?.rvalue(mem)?;
+        Ok((l, r))
     }
 
     pub fn compile(
-        &self,
-        var: &mut HashMap<Vec<char>, usize>,
+        self,
+        var: &mut HashMap<Symbol, usize>,
         mem: &mut Vec<i32>,
     ) -> Result<RetType, Error> {
-        match self.ty {
+        match self.ty() {
             Ntype::Add => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    ch.0.compile(var, mem)?.rvalue(mem)? + ch.1.compile(var, mem)?.rvalue(mem)?,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num(l.wrapping_add(r)))
             }
             Ntype::Sub => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    ch.0.compile(var, mem)?.rvalue(mem)? - ch.1.compile(var, mem)?.rvalue(mem)?,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num(l.wrapping_sub(r)))
             }
             Ntype::Mul => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    ch.0.compile(var, mem)?.rvalue(mem)? * ch.1.compile(var, mem)?.rvalue(mem)?,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num(l.wrapping_mul(r)))
             }
             Ntype::Div => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    ch.0.compile(var, mem)?.rvalue(mem)? / ch.1.compile(var, mem)?.rvalue(mem)?,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                if r == 0 {
+                    return Err(Error::new(ErrorKind::DivisionByZero, "division by zero"));
+                }
+                Ok(RetType::Num(l.wrapping_div(r)))
+            }
+            Ntype::Neg => {
+                let n = self.expect_child(0)?.compile(var, mem)?.rvalue(mem)?;
+                Ok(RetType::Num(n.wrapping_neg()))
             }
             Ntype::Eq => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    (ch.0.compile(var, mem)?.rvalue(mem)? == ch.1.compile(var, mem)?.rvalue(mem)?)
-                        as i32,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l == r) as i32))
             }
             Ntype::Neq => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    (ch.0.compile(var, mem)?.rvalue(mem)? != ch.1.compile(var, mem)?.rvalue(mem)?)
-                        as i32,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l != r) as i32))
             }
             Ntype::Greater => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    (ch.0.compile(var, mem)?.rvalue(mem)? > ch.1.compile(var, mem)?.rvalue(mem)?)
-                        as i32,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l > r) as i32))
             }
             Ntype::GreaterEq => {
-                let ch = self.expect_child()?;
-                Ok(RetType::Num(
-                    (ch.0.compile(var, mem)?.rvalue(mem)? >= ch.1.compile(var, mem)?.rvalue(mem)?)
-                        as i32,
-                ))
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l >= r) as i32))
+            }
+            Ntype::Less => {
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l < r) as i32))
+            }
+            Ntype::LessEq => {
+                let (l, r) = self.binary(var, mem)?;
+                Ok(RetType::Num((l <= r) as i32))
             }
             Ntype::Assign => {
-                let ch = self.expect_child()?;
-                let address = ch.1.compile(var, mem)?.expect_address()?;
-                mem[address] = ch.0.compile(var, mem)?.rvalue(mem)?;
+                let address = self.expect_child(0)?.compile(var, mem)?.expect_address()?;
+                mem[address] = self.expect_child(1)?.compile(var, mem)?.rvalue(mem)?;
                 Ok(RetType::Address(address))
             }
             Ntype::Num => {
-                if let DataUnion::Num(n) = self.value {
-                    Ok(RetType::Num(n))
+                if let DataUnion::Num(n) = self.value() {
+                    Ok(RetType::Num(*n))
                 } else {
                     Err(Error::new(ErrorKind::UnexpectedError, "unexpected error."))
                 }
             }
             Ntype::Lvar => {
-                if let DataUnion::String(s) = &self.value {
+                if let DataUnion::Symbol(s) = self.value() {
                     if var.contains_key(s) {
                         Ok(RetType::Address(var[s]))
                     } else {
                         let a = mem.len();
-                        var.insert(s.clone(), a);
+                        var.insert(*s, a);
                         mem.push(0);
                         Ok(RetType::Address(a))
                     }
@@ -286,17 +158,20 @@ impl Node {
         }
     }
 
-    fn format_debug(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
-        if let Some(ch) = &self.childs {
-            ch.0.format_debug(f, indent + 1)?;
+    /// 子の前半をノードの上に、後半を下に書く
+    fn format_debug(self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        let childs = self.ast.childs_of(self.id);
+        let (above, below) = childs.split_at(childs.len() / 2);
+        for &ch in above {
+            Node::new(self.ast, ch).format_debug(f, indent + 1)?;
         }
-        write!(f, "{}[type:{:?}", "\t|".repeat(indent), self.ty)?;
-        match self.value {
+        write!(f, "{}[type:{:?}", "\t|".repeat(indent), self.ty())?;
+        match self.value() {
             DataUnion::None => writeln!(f, "]"),
-            _ => writeln!(f, ", value:{:?}]", self.value),
+            v => writeln!(f, ", value:{:?}]", v),
         }?;
-        if let Some(ch) = &self.childs {
-            ch.1.format_debug(f, indent + 1)?;
+        for &ch in below {
+            Node::new(self.ast, ch).format_debug(f, indent + 1)?;
         }
         Ok(())
     }
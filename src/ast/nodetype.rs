@@ -7,10 +7,13 @@ pub enum Ntype {
     Sub,
     Mul,
     Div,
+    Neg,
     Eq,
     Neq,
     Greater,
     GreaterEq,
+    Less,
+    LessEq,
     Assign,
 }
 
@@ -0,0 +1,151 @@
+use super::{Ast, NodeId, Ntype};
+use crate::datatype::DataUnion;
+use crate::error::{Error, ErrorKind};
+use crate::tokenize::{TokenGen, TokenKind};
+
+impl Ast {
+    pub fn stmt(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let node = self.expr(token)?;
+        token.expect(DataUnion::char(';'))?;
+        Ok(node)
+    }
+
+    fn expr(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        self.assign(token)
+    }
+
+    fn assign(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        let node = self.equality(token)?;
+        let end = token.last_end();
+        if token.consume(DataUnion::char('='))? {
+            if !self.node(node).is_lvalue() {
+                token.error_span("left side of assignment is not an lvalue.", (start, end));
+                return Err(Error::new(ErrorKind::InvalidLvalue, "左辺値ではありません")
+                    .with_span((start, end)));
+            }
+            let rstart = token.pos();
+            let rhs = self.assign(token)?;
+            if self.node(rhs).ty() == Ntype::Assign {
+                let span = (rstart, token.last_end());
+                token.error_span("assignment cannot be used as an expression.", span);
+                return Err(
+                    Error::new(ErrorKind::InvalidLvalue, "代入は式として使えません")
+                        .with_span(span),
+                );
+            }
+            Ok(self.from_childs(Ntype::Assign, &[node, rhs], (start, token.last_end())))
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn equality(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        let mut node = self.relational(token)?;
+        loop {
+            let ty = if token.consume(DataUnion::str("=="))? {
+                Ntype::Eq
+            } else if token.consume(DataUnion::str("!="))? {
+                Ntype::Neq
+            } else {
+                break;
+            };
+            let rhs = self.relational(token)?;
+            node = self.from_childs(ty, &[node, rhs], (start, token.last_end()));
+        }
+        Ok(node)
+    }
+
+    fn relational(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        let mut node = self.add(token)?;
+        loop {
+            let ty = if token.consume(DataUnion::str(">"))? {
+                Ntype::Greater
+            } else if token.consume(DataUnion::str("<"))? {
+                Ntype::Less
+            } else if token.consume(DataUnion::str(">="))? {
+                Ntype::GreaterEq
+            } else if token.consume(DataUnion::str("<="))? {
+                Ntype::LessEq
+            } else {
+                break;
+            };
+            let rhs = self.relational(token)?;
+            node = self.from_childs(ty, &[node, rhs], (start, token.last_end()));
+        }
+        Ok(node)
+    }
+
+    fn add(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        let mut node = self.mul(token)?;
+
+        while !token.current().is_kind(TokenKind::Eof) {
+            let ty = if token.consume(DataUnion::char('+'))? {
+                Ntype::Add
+            } else if token.consume(DataUnion::char('-'))? {
+                Ntype::Sub
+            } else {
+                return Ok(node);
+            };
+            let rhs = self.mul(token)?;
+            node = self.from_childs(ty, &[node, rhs], (start, token.last_end()));
+        }
+        Ok(node)
+    }
+
+    fn mul(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        let mut node = self.unary(token)?;
+
+        while !token.current().is_kind(TokenKind::Eof) {
+            let ty = if token.consume(DataUnion::char('*'))? {
+                Ntype::Mul
+            } else if token.consume(DataUnion::char('/'))? {
+                Ntype::Div
+            } else {
+                return Ok(node);
+            };
+            let rhs = self.unary(token)?;
+            node = self.from_childs(ty, &[node, rhs], (start, token.last_end()));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        if token.consume(DataUnion::char('+'))? {
+            self.primary(token)
+        } else if token.consume(DataUnion::char('-'))? {
+            let operand = self.primary(token)?;
+            Ok(self.from_childs(Ntype::Neg, &[operand], (start, token.last_end())))
+        } else {
+            self.primary(token)
+        }
+    }
+
+    fn primary(&mut self, token: &mut TokenGen) -> Result<NodeId, Error> {
+        let start = token.pos();
+        if token.consume(DataUnion::char('('))? {
+            let node = self.expr(token)?;
+            token.expect(DataUnion::char(')'))?;
+            if self.node(node).ty() == Ntype::Assign {
+                let span = (start, token.last_end());
+                token.error_span("assignment cannot be used as an expression.", span);
+                return Err(
+                    Error::new(ErrorKind::InvalidLvalue, "代入は式として使えません")
+                        .with_span(span),
+                );
+            }
+            Ok(node)
+        } else if token.consume_kind(TokenKind::Number) {
+            let n = token.get_number()?;
+            Ok(self.number(n, (start, token.last_end())))
+        } else {
+            let name = token.get_ident()?;
+            Ok(self.lvar(name, (start, token.last_end())))
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{Node, Ntype, Program, Visitor};
+use crate::datatype::DataUnion;
+use crate::tokenize::TokenGen;
+
+impl<'a> Node<'a> {
+    /// 結合の強さ。大きいほど強い。
+    fn prec(self) -> u8 {
+        match self.ty() {
+            Ntype::Assign => 0,
+            Ntype::Eq | Ntype::Neq => 1,
+            Ntype::Greater | Ntype::GreaterEq | Ntype::Less | Ntype::LessEq => 2,
+            Ntype::Add | Ntype::Sub => 3,
+            Ntype::Mul | Ntype::Div => 4,
+            Ntype::Neg => 5,
+            Ntype::Num => match self.value() {
+                DataUnion::Num(n) if *n < 0 => 5,
+                _ => 6,
+            },
+            Ntype::Lvar => 6,
+        }
+    }
+
+    /// 演算子の優先順位から最小限の括弧を付けてソースに戻す
+    pub fn to_source(self) -> String {
+        let mut out = String::new();
+        SourceWriter { out: &mut out }.visit_node(self);
+        out
+    }
+
+    /// 位置を除いて木の形と値が同じかどうか
+    pub fn same_tree(self, other: Node<'_>) -> bool {
+        self.ty() == other.ty()
+            && self.value() == other.value()
+            && self.childs().len() == other.childs().len()
+            && self
+                .childs()
+                .zip(other.childs())
+                .all(|(a, b)| a.same_tree(b))
+    }
+}
+
+/// 子に要る最小の強さを見て括弧を付けながら書き出す。
+/// 比較は右結合で、単項の`-`は一次式しか取らない。
+struct SourceWriter<'o> {
+    out: &'o mut String,
+}
+
+impl<'a> SourceWriter<'_> {
+    fn binary(&mut self, node: Node<'a>, op: &str, left: u8, right: u8) {
+        self.operand(node.child(0).unwrap(), left);
+        *self.out += &format!(" {} ", op);
+        self.operand(node.child(1).unwrap(), right);
+    }
+
+    fn operand(&mut self, node: Node<'a>, min: u8) {
+        if node.prec() < min {
+            *self.out += "(";
+            self.visit_node(node);
+            *self.out += ")";
+        } else {
+            self.visit_node(node);
+        }
+    }
+
+    fn leaf(&mut self, node: Node<'a>) {
+        match node.value() {
+            DataUnion::Num(n) => *self.out += &n.to_string(),
+            DataUnion::Symbol(s) => *self.out += s.as_str(),
+            DataUnion::None => {}
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for SourceWriter<'_> {
+    fn visit_num(&mut self, node: Node<'a>) {
+        self.leaf(node);
+    }
+
+    fn visit_lvar(&mut self, node: Node<'a>) {
+        self.leaf(node);
+    }
+
+    fn visit_neg(&mut self, node: Node<'a>) {
+        *self.out += "-";
+        self.operand(node.child(0).unwrap(), 6);
+    }
+
+    fn visit_assign(&mut self, node: Node<'a>) {
+        self.binary(node, "=", 6, 1);
+    }
+
+    fn visit_eq(&mut self, node: Node<'a>) {
+        self.binary(node, "==", 1, 2);
+    }
+
+    fn visit_neq(&mut self, node: Node<'a>) {
+        self.binary(node, "!=", 1, 2);
+    }
+
+    fn visit_greater(&mut self, node: Node<'a>) {
+        self.binary(node, ">", 3, 2);
+    }
+
+    fn visit_greater_eq(&mut self, node: Node<'a>) {
+        self.binary(node, ">=", 3, 2);
+    }
+
+    fn visit_less(&mut self, node: Node<'a>) {
+        self.binary(node, "<", 3, 2);
+    }
+
+    fn visit_less_eq(&mut self, node: Node<'a>) {
+        self.binary(node, "<=", 3, 2);
+    }
+
+    fn visit_add(&mut self, node: Node<'a>) {
+        self.binary(node, "+", 3, 4);
+    }
+
+    fn visit_sub(&mut self, node: Node<'a>) {
+        self.binary(node, "-", 3, 4);
+    }
+
+    fn visit_mul(&mut self, node: Node<'a>) {
+        self.binary(node, "*", 4, 5);
+    }
+
+    fn visit_div(&mut self, node: Node<'a>) {
+        self.binary(node, "/", 4, 5);
+    }
+}
+
+impl Program {
+    /// 一行に一文の決まった書式のソースにする。
+    /// `src`を渡すとそのコメントと(一行までの)空行を残す。
+    pub fn to_source(&self, src: Option<&TokenGen>) -> String {
+        let comments = src.map_or(&[][..], |s| s.comments());
+        let mut out = String::new();
+        let mut next = 0;
+        // 直前に書いたものの終わりの位置
+        let mut last: Option<usize> = None;
+        let mut flush = |out: &mut String, last: &mut Option<usize>, until: usize| {
+            let Some(src) = src else { return };
+            while let Some(&(start, end)) = comments.get(next).filter(|c| c.0 < until) {
+                let text = src.text((start, end));
+                match *last {
+                    Some(l) if l <= start && src.line_of(l) == src.line_of(start) => {
+                        out.pop();
+                        *out += &format!(" {}\n", text.trim_end());
+                    }
+                    _ => {
+                        blank_line(src, out, *last, start);
+                        *out += &format!("{}\n", text.trim_end());
+                    }
+                }
+                *last = Some(end);
+                next += 1;
+            }
+        };
+        for stmt in self.stmts() {
+            let (start, end) = stmt.span();
+            flush(&mut out, &mut last, end);
+            if let Some(src) = src {
+                blank_line(src, &mut out, last, start);
+            }
+            out += &format!("{};\n", stmt.to_source());
+            last = Some(end);
+        }
+        flush(&mut out, &mut last, usize::MAX);
+        out
+    }
+
+    /// 位置を除いて文の並びが同じかどうか
+    pub fn same_tree(&self, other: &Program) -> bool {
+        self.stmts().len() == other.stmts().len()
+            && self.stmts().zip(other.stmts()).all(|(a, b)| a.same_tree(b))
+    }
+}
+
+/// 元のソースで`last`と`start`の間に空行があれば一行だけ空ける
+fn blank_line(src: &TokenGen, out: &mut String, last: Option<usize>, start: usize) {
+    if let Some(l) = last {
+        if src.line_of(start) > src.line_of(l) + 1 {
+            *out += "\n";
+        }
+    }
+}
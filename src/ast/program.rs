@@ -1,26 +1,65 @@
-use super::Node;
+use super::{Ast, Fold, Node, NodeId, Visitor, VisitorMut};
 use crate::error::Error;
+use crate::symbol::Symbol;
 use crate::tokenize::{TokenGen, TokenKind};
 use std::collections::HashMap;
 pub struct Program {
-    stmts: Vec<Node>,
+    ast: Ast,
+    stmts: Vec<NodeId>,
 }
 
 impl Program {
     pub fn from_tokens(token: &mut TokenGen) -> Result<Self, Error> {
-        let mut nodes = Vec::new();
+        let mut ast = Ast::new();
+        let mut stmts = Vec::new();
         while !token.current().is_kind(TokenKind::Eof) {
-            nodes.push(Node::stmt(token)?);
+            stmts.push(ast.stmt(token)?);
         }
-        Ok(Self { stmts: nodes })
+        Ok(Self { ast, stmts })
+    }
+
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    pub fn stmts(&self) -> impl ExactSizeIterator<Item = Node<'_>> + '_ {
+        self.stmts.iter().map(|&id| self.ast.node(id))
+    }
+
+    pub fn visit<'a, V>(&'a self, v: &mut V)
+    where
+        V: Visitor<'a> + ?Sized,
+    {
+        for n in self.stmts() {
+            v.visit_node(n);
+        }
+    }
+
+    pub fn visit_mut<V>(&mut self, v: &mut V)
+    where
+        V: VisitorMut + ?Sized,
+    {
+        for &id in &self.stmts {
+            v.visit_node_mut(&mut self.ast, id);
+        }
+    }
+
+    /// 各文を`f`で畳み込んだ新しいプログラムを作る
+    pub fn fold<F>(&self, f: &mut F) -> Program
+    where
+        F: Fold + ?Sized,
+    {
+        let mut ast = Ast::new();
+        let stmts = self.stmts().map(|n| f.fold_node(n, &mut ast)).collect();
+        Program { ast, stmts }
     }
 
     pub fn compile(
         &self,
-        var: &mut HashMap<Vec<char>, usize>,
+        var: &mut HashMap<Symbol, usize>,
         mem: &mut Vec<i32>,
     ) -> Result<(), Error> {
-        for n in &self.stmts {
+        for n in self.stmts() {
             n.compile(var, mem)?;
         }
         Ok(())
@@ -29,7 +68,7 @@ impl Program {
 
 impl std::fmt::Debug for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (i, tr) in self.stmts.iter().enumerate() {
+        for (i, tr) in self.stmts().enumerate() {
             writeln!(f, "{}th statement:\n{:?}", i, tr)?;
         }
         Ok(())
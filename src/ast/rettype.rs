@@ -13,7 +13,7 @@ impl RetType {
             Err(Error::new(ErrorKind::TypeError, "expect number"))
         }
     }
-    pub fn rvalue(&self, mem: &Vec<i32>) -> Result<i32, Error> {
+    pub fn rvalue(&self, mem: &[i32]) -> Result<i32, Error> {
         match self {
             Self::Num(n) => Ok(*n),
             Self::Address(a) => Ok(mem[*a]),
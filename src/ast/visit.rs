@@ -0,0 +1,277 @@
+use super::{Ast, Node, NodeId, Ntype};
+
+/// 構文木を読み取りだけで走査する。
+/// `visit_node`は種類ごとのメソッドに振り分け、それらは既定で子をたどる。
+pub trait Visitor<'a> {
+    fn visit_node(&mut self, node: Node<'a>) {
+        walk_node(self, node);
+    }
+
+    fn visit_num(&mut self, _node: Node<'a>) {}
+
+    fn visit_lvar(&mut self, _node: Node<'a>) {}
+
+    fn visit_add(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_sub(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_mul(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_div(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_neg(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_eq(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_neq(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_greater(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_greater_eq(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_less(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_less_eq(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+
+    fn visit_assign(&mut self, node: Node<'a>) {
+        walk_childs(self, node);
+    }
+}
+
+pub fn walk_node<'a, V>(v: &mut V, node: Node<'a>)
+where
+    V: Visitor<'a> + ?Sized,
+{
+    match node.ty() {
+        Ntype::Num => v.visit_num(node),
+        Ntype::Lvar => v.visit_lvar(node),
+        Ntype::Add => v.visit_add(node),
+        Ntype::Sub => v.visit_sub(node),
+        Ntype::Mul => v.visit_mul(node),
+        Ntype::Div => v.visit_div(node),
+        Ntype::Neg => v.visit_neg(node),
+        Ntype::Eq => v.visit_eq(node),
+        Ntype::Neq => v.visit_neq(node),
+        Ntype::Greater => v.visit_greater(node),
+        Ntype::GreaterEq => v.visit_greater_eq(node),
+        Ntype::Less => v.visit_less(node),
+        Ntype::LessEq => v.visit_less_eq(node),
+        Ntype::Assign => v.visit_assign(node),
+    }
+}
+
+pub fn walk_childs<'a, V>(v: &mut V, node: Node<'a>)
+where
+    V: Visitor<'a> + ?Sized,
+{
+    for ch in node.childs() {
+        v.visit_node(ch);
+    }
+}
+
+/// 構文木をその場で書き換えながら走査する
+pub trait VisitorMut {
+    fn visit_node_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_node_mut(self, ast, id);
+    }
+
+    fn visit_num_mut(&mut self, _ast: &mut Ast, _id: NodeId) {}
+
+    fn visit_lvar_mut(&mut self, _ast: &mut Ast, _id: NodeId) {}
+
+    fn visit_add_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_sub_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_mul_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_div_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_neg_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_eq_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_neq_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_greater_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_greater_eq_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_less_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_less_eq_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+
+    fn visit_assign_mut(&mut self, ast: &mut Ast, id: NodeId) {
+        walk_childs_mut(self, ast, id);
+    }
+}
+
+pub fn walk_node_mut<V>(v: &mut V, ast: &mut Ast, id: NodeId)
+where
+    V: VisitorMut + ?Sized,
+{
+    match ast.node(id).ty() {
+        Ntype::Num => v.visit_num_mut(ast, id),
+        Ntype::Lvar => v.visit_lvar_mut(ast, id),
+        Ntype::Add => v.visit_add_mut(ast, id),
+        Ntype::Sub => v.visit_sub_mut(ast, id),
+        Ntype::Mul => v.visit_mul_mut(ast, id),
+        Ntype::Div => v.visit_div_mut(ast, id),
+        Ntype::Neg => v.visit_neg_mut(ast, id),
+        Ntype::Eq => v.visit_eq_mut(ast, id),
+        Ntype::Neq => v.visit_neq_mut(ast, id),
+        Ntype::Greater => v.visit_greater_mut(ast, id),
+        Ntype::GreaterEq => v.visit_greater_eq_mut(ast, id),
+        Ntype::Less => v.visit_less_mut(ast, id),
+        Ntype::LessEq => v.visit_less_eq_mut(ast, id),
+        Ntype::Assign => v.visit_assign_mut(ast, id),
+    }
+}
+
+pub fn walk_childs_mut<V>(v: &mut V, ast: &mut Ast, id: NodeId)
+where
+    V: VisitorMut + ?Sized,
+{
+    for i in 0..ast.childs_of(id).len() {
+        let ch = ast.childs_of(id)[i];
+        v.visit_node_mut(ast, ch);
+    }
+}
+
+/// 構文木から新しい構文木を`dst`に組み立てる。
+/// 種類ごとのメソッドは既定で子を畳み込んだ上でノードを複製する。
+pub trait Fold {
+    fn fold_node(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        walk_fold(self, node, dst)
+    }
+
+    fn fold_num(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_lvar(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_add(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_sub(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_mul(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_div(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_neg(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_eq(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_neq(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_greater(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_greater_eq(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_less(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_less_eq(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+
+    fn fold_assign(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+        fold_childs(self, node, dst)
+    }
+}
+
+pub fn walk_fold<F>(f: &mut F, node: Node<'_>, dst: &mut Ast) -> NodeId
+where
+    F: Fold + ?Sized,
+{
+    match node.ty() {
+        Ntype::Num => f.fold_num(node, dst),
+        Ntype::Lvar => f.fold_lvar(node, dst),
+        Ntype::Add => f.fold_add(node, dst),
+        Ntype::Sub => f.fold_sub(node, dst),
+        Ntype::Mul => f.fold_mul(node, dst),
+        Ntype::Div => f.fold_div(node, dst),
+        Ntype::Neg => f.fold_neg(node, dst),
+        Ntype::Eq => f.fold_eq(node, dst),
+        Ntype::Neq => f.fold_neq(node, dst),
+        Ntype::Greater => f.fold_greater(node, dst),
+        Ntype::GreaterEq => f.fold_greater_eq(node, dst),
+        Ntype::Less => f.fold_less(node, dst),
+        Ntype::LessEq => f.fold_less_eq(node, dst),
+        Ntype::Assign => f.fold_assign(node, dst),
+    }
+}
+
+pub fn fold_childs<F>(f: &mut F, node: Node<'_>, dst: &mut Ast) -> NodeId
+where
+    F: Fold + ?Sized,
+{
+    let childs: Vec<NodeId> = node.childs().map(|ch| f.fold_node(ch, dst)).collect();
+    dst.push(node.ty(), node.value().clone(), &childs, node.span())
+}
@@ -1,6 +1,8 @@
+use crate::symbol::Symbol;
+
 #[derive(Clone, PartialEq, Eq, PartialOrd)]
 pub enum DataUnion {
-    String(Vec<char>),
+    Symbol(Symbol),
     Num(i32),
     None,
 }
@@ -10,7 +12,7 @@ impl std::fmt::Debug for DataUnion {
         match self {
             DataUnion::None => write!(f, "None"),
             DataUnion::Num(n) => write!(f, "{n:?}"),
-            DataUnion::String(s) => write!(f, "{:?}", s.iter().collect::<String>()),
+            DataUnion::Symbol(s) => write!(f, "{:?}", s),
         }
     }
 }
@@ -21,14 +23,14 @@ impl DataUnion {
     }
 
     pub fn str(s: &str) -> Self {
-        Self::String(s.chars().collect())
+        Self::Symbol(Symbol::intern(s))
     }
 
     pub fn char(c: char) -> Self {
-        Self::String(vec![c])
+        Self::Symbol(Symbol::intern(c.encode_utf8(&mut [0; 4])))
     }
 
     pub fn cstr(s: &[char]) -> Self {
-        Self::String(s.to_vec())
+        Self::Symbol(Symbol::from_chars(s))
     }
 }
@@ -0,0 +1,5 @@
+mod command;
+mod session;
+
+pub use command::{Radix, Reply, HELP};
+pub use session::{Debugger, Point, Stop};
@@ -0,0 +1,240 @@
+use super::{Debugger, Point, Stop};
+use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
+use std::fmt::Write;
+
+/// `continue`で止まらないまま回す最大のサイクル数
+const CONTINUE_LIMIT: u64 = 100_000;
+
+pub const HELP: &str = "\
+step [N]            run N statements
+cycle [N]           run N cycles, stopping at breakpoints
+continue            run until a breakpoint or watchpoint
+print[/FMT] [EXPR]  print an expression, or every variable (FMT: d u x o b)
+break LINE          stop before the statement on LINE
+break if EXPR       stop when EXPR becomes true
+watch NAME          stop when NAME changes
+delete ID           remove a breakpoint or watchpoint
+info                list breakpoints, watchpoints and forced variables
+force NAME VALUE    hold NAME at VALUE
+release NAME        stop forcing NAME
+set NAME VALUE      write VALUE to NAME once
+where               show the cycle and the next statement
+load PATH           load another design
+quit                leave
+";
+
+/// コマンド一つの結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Text(String),
+    Quit,
+}
+
+/// 値を表示する基数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Signed,
+    Unsigned,
+    Hex,
+    Octal,
+    Binary,
+}
+
+impl Radix {
+    pub fn parse(name: &str) -> Result<Radix, Error> {
+        Ok(match name {
+            "d" => Radix::Signed,
+            "u" => Radix::Unsigned,
+            "x" => Radix::Hex,
+            "o" => Radix::Octal,
+            "b" => Radix::Binary,
+            _ => return Err(invalid(format!("unknown format: {}", name))),
+        })
+    }
+
+    pub fn format(self, value: i32) -> String {
+        let u = value as u32;
+        match self {
+            Radix::Signed => value.to_string(),
+            Radix::Unsigned => u.to_string(),
+            Radix::Hex => format!("{:#x}", u),
+            Radix::Octal => format!("{:#o}", u),
+            Radix::Binary => format!("{:#b}", u),
+        }
+    }
+}
+
+impl Debugger {
+    /// 対話モードのコマンドを一行実行する
+    pub fn execute(&mut self, line: &str) -> Result<Reply, Error> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let (cmd, fmt) = cmd.split_once('/').unwrap_or((cmd, ""));
+        let text = match cmd {
+            "" => String::new(),
+            "s" | "step" => {
+                let n = count(rest)?;
+                let mut stop = None;
+                for _ in 0..n {
+                    stop = self.step()?;
+                    if stop.is_some() {
+                        break;
+                    }
+                }
+                self.report(stop)
+            }
+            "c" | "cycle" => {
+                let stop = self.run_cycles(count(rest)?)?;
+                self.report(stop)
+            }
+            "continue" => {
+                let start = self.cycle();
+                let stop = self.run_cycles(CONTINUE_LIMIT)?;
+                if stop.is_none() {
+                    return Err(invalid(format!(
+                        "no stop within {} cycles (from cycle {})",
+                        CONTINUE_LIMIT, start
+                    )));
+                }
+                self.report(stop)
+            }
+            "p" | "print" => {
+                let radix = if fmt.is_empty() {
+                    Radix::Signed
+                } else {
+                    Radix::parse(fmt)?
+                };
+                if rest.is_empty() {
+                    let mut s = String::new();
+                    for (name, value) in self.vars() {
+                        writeln!(s, "{} = {}", name, radix.format(value)).unwrap();
+                    }
+                    s
+                } else {
+                    format!("{} = {}\n", rest, radix.format(self.eval(rest)?))
+                }
+            }
+            "b" | "break" => {
+                let id = match rest.strip_prefix("if ") {
+                    Some(expr) => self.break_if(expr)?,
+                    None => {
+                        let line = rest
+                            .parse()
+                            .map_err(|_| invalid("break requires a line or `if EXPR`"))?;
+                        self.break_line(line)?
+                    }
+                };
+                format!("breakpoint {}\n", id)
+            }
+            "w" | "watch" => {
+                let id = self.watch(name(rest)?)?;
+                format!("watchpoint {}\n", id)
+            }
+            "d" | "delete" => {
+                let id = rest.parse().map_err(|_| invalid("delete requires an id"))?;
+                if !self.delete(id) {
+                    return Err(invalid(format!("no breakpoint {}", id)));
+                }
+                String::new()
+            }
+            "i" | "info" => self.info(),
+            "force" | "set" => {
+                let (n, v) = rest
+                    .split_once(' ')
+                    .ok_or_else(|| invalid(format!("{} requires a name and a value", cmd)))?;
+                let value = self.eval(v)?;
+                if cmd == "force" {
+                    self.force(name(n)?, value)?;
+                } else {
+                    self.set(name(n)?, value)?;
+                }
+                String::new()
+            }
+            "release" => {
+                if !self.release(name(rest)?) {
+                    return Err(invalid(format!("{} is not forced", rest)));
+                }
+                String::new()
+            }
+            "where" => self.report(None),
+            "load" => {
+                let code = std::fs::read_to_string(rest)
+                    .map_err(|e| Error::new(ErrorKind::CannotReadFile, e))?;
+                *self = Debugger::new(&code)?;
+                String::new()
+            }
+            "h" | "help" => HELP.to_string(),
+            "q" | "quit" => return Ok(Reply::Quit),
+            _ => return Err(invalid(format!("unknown command: {}", cmd))),
+        };
+        Ok(Reply::Text(text))
+    }
+
+    /// 止まった理由と、次に実行する文
+    fn report(&self, stop: Option<Stop>) -> String {
+        let mut s = String::new();
+        match stop {
+            Some(Stop::Line { id, line }) => writeln!(s, "breakpoint {} at line {}", id, line),
+            Some(Stop::Cond { id }) => writeln!(s, "breakpoint {}", id),
+            Some(Stop::Watch { id, old, new }) => {
+                writeln!(s, "watchpoint {}: {} -> {}", id, old, new)
+            }
+            None => Ok(()),
+        }
+        .unwrap();
+        match self.program().stmts().nth(self.pc()) {
+            Some(stmt) => writeln!(
+                s,
+                "cycle {}, line {}: {};",
+                self.cycle(),
+                self.line(self.pc()),
+                stmt.to_source()
+            ),
+            None => writeln!(s, "cycle {}", self.cycle()),
+        }
+        .unwrap();
+        s
+    }
+
+    fn info(&self) -> String {
+        let mut s = String::new();
+        for (id, p) in self.points() {
+            match p {
+                Point::Line(line) => writeln!(s, "{}: break at line {}", id, line),
+                Point::Cond { text, .. } => writeln!(s, "{}: break if {}", id, text),
+                Point::Watch { name, .. } => writeln!(s, "{}: watch {}", id, name),
+            }
+            .unwrap();
+        }
+        let mut forced: Vec<_> = self.forced().collect();
+        forced.sort_by_key(|f| f.0.to_string());
+        for (name, value) in forced {
+            writeln!(s, "forced {} = {}", name, value).unwrap();
+        }
+        s
+    }
+}
+
+fn count(arg: &str) -> Result<u64, Error> {
+    if arg.is_empty() {
+        return Ok(1);
+    }
+    arg.parse()
+        .map_err(|_| invalid(format!("invalid count: {}", arg)))
+}
+
+/// 変数名として読める語
+fn name(arg: &str) -> Result<Symbol, Error> {
+    let ok = arg.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !ok {
+        return Err(invalid(format!("not a variable name: {}", arg)));
+    }
+    Ok(Symbol::intern(arg))
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidArgument, msg.into())
+}
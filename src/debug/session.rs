@@ -0,0 +1,277 @@
+use crate::ast::{Ntype, Program};
+use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
+use crate::tokenize::TokenGen;
+use crate::vm::{Code, Vm};
+use std::collections::HashMap;
+
+/// 停止する条件
+pub enum Point {
+    /// 行`line`で最初に始まる文を実行する前
+    Line(usize),
+    /// 式が偽から真に変わった文の後
+    Cond { expr: Vm, text: String, last: bool },
+    /// 変数の値が変わった文の後
+    Watch { name: Symbol, last: i32 },
+}
+
+/// 止まった理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Line { id: usize, line: usize },
+    Cond { id: usize },
+    Watch { id: usize, old: i32, new: i32 },
+}
+
+/// バイトコードの`Vm`を文ごとに進めるデバッガ。
+///
+/// 状態は`Vm`のレジスタそのもので、プログラムを最後の文まで実行するとサイクルが一つ進む。
+/// 式や条件も`Vm`にコンパイルし、同じ名前の変数の値を写して評価する。
+///
+/// ネットリストのシミュレータ(`CycleSim`、`EventSim`)とはつながっていない。
+/// ネットリストには文や行の情報が残らず、行のブレークポイントを置けないため。
+/// 値はどちらも同じになる(`cycle_sim_matches_event_sim`と`event_sim`のテストを参照)。
+pub struct Debugger {
+    prog: Program,
+    /// 各文の始まる行
+    lines: Vec<usize>,
+    vm: Vm,
+    /// 次に実行する文
+    pc: usize,
+    cycle: u64,
+    points: Vec<(usize, Point)>,
+    next_id: usize,
+    forced: HashMap<Symbol, i32>,
+    /// 今の文の前の行ブレークポイントで止まったところか
+    at_break: bool,
+}
+
+impl Debugger {
+    pub fn new(code: &str) -> Result<Self, Error> {
+        let mut tokens = TokenGen::from_code(code)?;
+        let prog = Program::from_tokens(&mut tokens)?;
+        let lines = prog.stmts().map(|s| tokens.line_of(s.span().0)).collect();
+        let vm = Vm::new(Code::compile(&prog)?);
+        Ok(Self {
+            prog,
+            lines,
+            vm,
+            pc: 0,
+            cycle: 0,
+            points: Vec::new(),
+            next_id: 1,
+            forced: HashMap::new(),
+            at_break: false,
+        })
+    }
+
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// 次に実行する文の番号
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.prog
+    }
+
+    /// 文`i`の始まる行
+    pub fn line(&self, i: usize) -> usize {
+        self.lines[i]
+    }
+
+    /// 変数の値。プログラムに出てこない変数は0。
+    pub fn value(&self, name: Symbol) -> i32 {
+        self.vm.get(name).unwrap_or(0)
+    }
+
+    /// 変数を木の評価順に並べる
+    pub fn vars(&self) -> Vec<(Symbol, i32)> {
+        let code = self.vm.code();
+        code.vars()
+            .iter()
+            .copied()
+            .zip(self.vm.vars().iter().copied())
+            .collect()
+    }
+
+    /// 式を今の状態で評価する。状態は変えない。
+    pub fn eval(&self, expr: &str) -> Result<i32, Error> {
+        self.eval_vm(&mut parse_expr(expr)?)
+    }
+
+    /// 式の`Vm`に今の変数の値を写して評価する
+    fn eval_vm(&self, expr: &mut Vm) -> Result<i32, Error> {
+        let vars = expr.code().vars().to_vec();
+        for name in vars {
+            expr.set(name, self.value(name));
+        }
+        expr.run_stmt(0)
+    }
+
+    /// 変数に値を一度だけ書く。次の代入で上書きされる。
+    pub fn set(&mut self, name: Symbol, value: i32) -> Result<(), Error> {
+        if !self.vm.set(name, value) {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("no variable named {}", name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `release`するまで変数を`value`に固定する
+    pub fn force(&mut self, name: Symbol, value: i32) -> Result<(), Error> {
+        self.set(name, value)?;
+        self.forced.insert(name, value);
+        Ok(())
+    }
+
+    pub fn release(&mut self, name: Symbol) -> bool {
+        self.forced.remove(&name).is_some()
+    }
+
+    pub fn forced(&self) -> impl Iterator<Item = (Symbol, i32)> + '_ {
+        self.forced.iter().map(|(&s, &v)| (s, v))
+    }
+
+    pub fn break_line(&mut self, line: usize) -> Result<usize, Error> {
+        if !self.lines.contains(&line) {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("no statement starts on line {}", line),
+            ));
+        }
+        Ok(self.add(Point::Line(line)))
+    }
+
+    pub fn break_if(&mut self, text: &str) -> Result<usize, Error> {
+        let mut expr = parse_expr(text)?;
+        let last = self.eval_vm(&mut expr)? != 0;
+        Ok(self.add(Point::Cond {
+            expr,
+            text: text.trim().to_string(),
+            last,
+        }))
+    }
+
+    pub fn watch(&mut self, name: Symbol) -> Result<usize, Error> {
+        let last = self.vm.get(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidArgument,
+                format!("no variable named {}", name),
+            )
+        })?;
+        Ok(self.add(Point::Watch { name, last }))
+    }
+
+    fn add(&mut self, point: Point) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.points.push((id, point));
+        id
+    }
+
+    pub fn delete(&mut self, id: usize) -> bool {
+        let len = self.points.len();
+        self.points.retain(|p| p.0 != id);
+        self.points.len() != len
+    }
+
+    pub fn points(&self) -> &[(usize, Point)] {
+        &self.points
+    }
+
+    /// 文を一つ実行する。ブレークポイントやウォッチポイントに当たればその理由を返す。
+    pub fn step(&mut self) -> Result<Option<Stop>, Error> {
+        if self.prog.stmts().len() == 0 {
+            self.cycle += 1;
+            return Ok(None);
+        }
+        if !std::mem::take(&mut self.at_break) {
+            let line = self.lines[self.pc];
+            let first = self.pc == 0 || self.lines[self.pc - 1] != line;
+            let hit = self.points.iter().find_map(|(id, p)| match p {
+                Point::Line(l) if first && *l == line => Some(*id),
+                _ => None,
+            });
+            if let Some(id) = hit {
+                self.at_break = true;
+                return Ok(Some(Stop::Line { id, line }));
+            }
+        }
+        self.vm.run_stmt(self.pc)?;
+        for (&name, &value) in &self.forced {
+            self.vm.set(name, value);
+        }
+        self.pc += 1;
+        if self.pc == self.lines.len() {
+            self.pc = 0;
+            self.cycle += 1;
+        }
+        self.check()
+    }
+
+    /// 文の後に止まる条件を調べ、覚えている値を今の値にする
+    fn check(&mut self) -> Result<Option<Stop>, Error> {
+        let mut stop = None;
+        let mut points = std::mem::take(&mut self.points);
+        let mut result = Ok(());
+        for (id, p) in &mut points {
+            match p {
+                Point::Line(_) => {}
+                Point::Cond { expr, last, .. } => match self.eval_vm(expr) {
+                    Ok(v) => {
+                        let now = v != 0;
+                        if now && !*last && stop.is_none() {
+                            stop = Some(Stop::Cond { id: *id });
+                        }
+                        *last = now;
+                    }
+                    Err(e) => result = Err(e),
+                },
+                Point::Watch { name, last } => {
+                    let now = self.value(*name);
+                    if now != *last && stop.is_none() {
+                        stop = Some(Stop::Watch {
+                            id: *id,
+                            old: *last,
+                            new: now,
+                        });
+                    }
+                    *last = now;
+                }
+            }
+        }
+        self.points = points;
+        result.map(|_| stop)
+    }
+
+    /// サイクルの区切りを`n`回越えるまで進める。途中で止まればその理由を返す。
+    pub fn run_cycles(&mut self, n: u64) -> Result<Option<Stop>, Error> {
+        let end = self.cycle + n;
+        while self.cycle < end {
+            if let Some(stop) = self.step()? {
+                return Ok(Some(stop));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 式を一つだけ読んで`Vm`にする。代入は状態を変えるので使えない。
+fn parse_expr(text: &str) -> Result<Vm, Error> {
+    let mut tokens = TokenGen::from_code(&format!("{};", text))?;
+    let prog = Program::from_tokens(&mut tokens)?;
+    let single = prog.stmts().len() == 1 && prog.stmts().all(|s| s.ty() != Ntype::Assign);
+    if !single {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            format!("not a single expression: {}", text),
+        ));
+    }
+    Ok(Vm::new(Code::compile(&prog)?))
+}
@@ -1,5 +1,5 @@
 pub mod kind;
-pub use kind::ErrorKind;
+pub use kind::{ErrorKind, Severity};
 
 #[derive(Debug)]
 enum _Error {
@@ -9,6 +9,8 @@ enum _Error {
 
 pub struct Error {
     _error: _Error,
+    /// ソース上の位置(文字単位の半開区間)
+    span: Option<(usize, usize)>,
 }
 
 #[allow(dead_code)]
@@ -19,6 +21,25 @@ impl Error {
     {
         Error {
             _error: _Error::Custom((kind, error.into())),
+            span: None,
+        }
+    }
+
+    /// エラーの起きたソース上の位置を付ける
+    pub fn with_span(mut self, span: (usize, usize)) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+
+    /// 作ったときに渡した詳しい説明
+    pub fn detail(&self) -> Option<String> {
+        match &self._error {
+            _Error::Simple(_) => None,
+            _Error::Custom(c) => Some(c.1.to_string()),
         }
     }
 
@@ -55,6 +76,7 @@ impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Error {
         Error {
             _error: _Error::Simple(kind),
+            span: None,
         }
     }
 }
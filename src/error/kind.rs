@@ -7,6 +7,17 @@ pub enum ErrorKind {
     InvalidData,
     UnexpectedError,
     TypeError,
+    InvalidLvalue,
+    DivisionByZero,
+    InvalidArgument,
+    SimulationError,
+    Lint(Severity),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
 }
 
 impl ErrorKind {
@@ -18,6 +29,29 @@ impl ErrorKind {
             ErrorKind::InvalidData => "終端トークンがデータを保持しています。",
             ErrorKind::UnexpectedError => "予期せぬエラー",
             ErrorKind::TypeError => "型エラー",
+            ErrorKind::InvalidLvalue => "不正な代入先",
+            ErrorKind::DivisionByZero => "ゼロ除算",
+            ErrorKind::InvalidArgument => "不正な引数",
+            ErrorKind::SimulationError => "シミュレーションエラー",
+            ErrorKind::Lint(Severity::Warning) => "警告",
+            ErrorKind::Lint(Severity::Error) => "リントエラー",
+        }
+    }
+
+    /// リントの警告以外はすべてエラー扱い
+    pub fn severity(self) -> Severity {
+        match self {
+            ErrorKind::Lint(s) => s,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
         }
     }
 }
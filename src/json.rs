@@ -0,0 +1,19 @@
+//! フロントエンドの結果のJSON出力。
+//!
+//! どの出力も`{"version": 1, ...}`というオブジェクトで、位置`span`はソースの
+//! 文字単位の半開区間`[start, end]`、`line`は1始まりの行番号。
+//!
+//! - トークン列: `{"version", "tokens": [{"kind", "value", "span"}], "comments": [span]}`。
+//!   `kind`は`"Number"`、`"Reserved"`、`"Ident"`のどれかで、`value`は数値か文字列。
+//! - 構文木: `{"version", "statements": [node]}`。`node`は
+//!   `{"id", "type", "value", "span", "children": [node]}`で、`type`は`Ntype`の名前、
+//!   `value`は`null`か数値か変数名。
+//! - 診断: `{"version", "diagnostics": [{"severity", "code", "message", "span", "line"}]}`。
+//!   `severity`は`"warning"`か`"error"`、`code`はリントのルール名か`ErrorKind`の名前。
+//!   位置がわからないエラーの`span`と`line`は`null`。
+
+mod export;
+mod value;
+
+pub use export::{ast, diagnostics, tokens, VERSION};
+pub use value::Json;
@@ -0,0 +1,91 @@
+use super::Json;
+use crate::ast::{Node, Program};
+use crate::datatype::DataUnion;
+use crate::error::{Error, ErrorKind};
+use crate::lint::Lint;
+use crate::tokenize::TokenGen;
+
+/// 出力の形式の版。互換性のない変更をしたら上げる。
+pub const VERSION: i64 = 1;
+
+fn value(v: &DataUnion) -> Json {
+    match v {
+        DataUnion::Num(n) => Json::Num(*n as i64),
+        DataUnion::Symbol(s) => s.as_str().into(),
+        DataUnion::None => Json::Null,
+    }
+}
+
+/// `src`のトークン列とコメントの位置
+pub fn tokens(src: &TokenGen) -> Result<Json, Error> {
+    let mut src = src.rewind()?;
+    let tokens = src
+        .read_all()?
+        .into_iter()
+        .map(|(t, span)| {
+            Json::obj([
+                ("kind", format!("{:?}", t.kind()).as_str().into()),
+                ("value", value(t.get_data())),
+                ("span", span.into()),
+            ])
+        })
+        .collect();
+    let comments = src.comments().iter().map(|&c| c.into()).collect();
+    Ok(Json::obj([
+        ("version", VERSION.into()),
+        ("tokens", Json::Arr(tokens)),
+        ("comments", Json::Arr(comments)),
+    ]))
+}
+
+fn node(n: Node<'_>) -> Json {
+    Json::obj([
+        ("id", (n.id().index() as i64).into()),
+        ("type", format!("{:?}", n.ty()).as_str().into()),
+        ("value", value(n.value())),
+        ("span", n.span().into()),
+        ("children", Json::Arr(n.childs().map(node).collect())),
+    ])
+}
+
+pub fn ast(prog: &Program) -> Json {
+    Json::obj([
+        ("version", VERSION.into()),
+        ("statements", Json::Arr(prog.stmts().map(node).collect())),
+    ])
+}
+
+/// リントと、あればパイプラインを止めたエラー`error`(とその位置)
+pub fn diagnostics(
+    lints: &[Lint],
+    error: Option<(&Error, Option<(usize, usize)>)>,
+    src: &TokenGen,
+) -> Json {
+    let diag = |severity: String, code: &str, msg: &str, span: Option<(usize, usize)>| {
+        Json::obj([
+            ("severity", severity.as_str().into()),
+            ("code", code.into()),
+            ("message", msg.into()),
+            ("span", span.into()),
+            ("line", span.map(|s| src.line_of(s.0) as i64).into()),
+        ])
+    };
+    let mut list: Vec<Json> = lints
+        .iter()
+        .map(|l| diag(l.severity.to_string(), l.rule, &l.msg, Some(l.span)))
+        .collect();
+    match error {
+        // リントのエラーは上で出している
+        Some((e, _)) if matches!(e.kind(), ErrorKind::Lint(_)) => {}
+        Some((e, span)) => {
+            let msg = e.detail().unwrap_or_else(|| e.kind().msg().to_string());
+            let code = format!("{:?}", e.kind());
+            list.push(diag(e.kind().severity().to_string(), &code, &msg, span));
+        }
+        None => {}
+    }
+    Json::obj([
+        ("version", VERSION.into()),
+        ("diagnostics", Json::Arr(list)),
+    ])
+}
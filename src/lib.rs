@@ -1,20 +1,50 @@
-mod ast;
-mod datatype;
-mod error;
-mod tokenize;
+pub mod aig;
+pub mod ast;
+pub mod datatype;
+pub mod debug;
+pub mod error;
+pub mod json;
+pub mod lint;
+pub mod netlist;
+pub mod pass;
+pub mod sim;
+pub mod symbol;
+pub mod tokenize;
+pub mod vcd;
+pub mod vm;
 #[macro_use]
 mod array;
 
 #[cfg(test)]
 mod test {
+    #[allow(unused_imports)]
+    use super::aig::*;
     #[allow(unused_imports)]
     use super::ast::*;
     #[allow(unused_imports)]
     use super::datatype::*;
     #[allow(unused_imports)]
+    use super::debug::*;
+    #[allow(unused_imports)]
     use super::error::*;
     #[allow(unused_imports)]
+    use super::json::*;
+    #[allow(unused_imports)]
+    use super::lint::*;
+    #[allow(unused_imports)]
+    use super::netlist::*;
+    #[allow(unused_imports)]
+    use super::pass::*;
+    #[allow(unused_imports)]
+    use super::sim::*;
+    #[allow(unused_imports)]
+    use super::symbol::*;
+    #[allow(unused_imports)]
     use super::tokenize::*;
+    #[allow(unused_imports)]
+    use super::vcd::*;
+    #[allow(unused_imports)]
+    use super::vm::*;
 
     #[test]
     fn it_works() {
@@ -27,6 +57,945 @@ mod test {
         println!("{:?}\n{:?}", var, mem);
     }
 
+    #[test]
+    fn lvalue_check() {
+        for code in [
+            "a = (b = c) = 7;",
+            "a + 1 = 3;",
+            "a = b = 7;",
+            "a = (b = 1) + 2;",
+        ] {
+            let mut tokens = TokenGen::from_code(code).unwrap();
+            let err = Program::from_tokens(&mut tokens).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::InvalidLvalue), "{}", code);
+        }
+        let mut tokens = TokenGen::from_code("a = (b + c) * 2;").unwrap();
+        assert!(Program::from_tokens(&mut tokens).is_ok());
+    }
+
+    #[test]
+    fn unary_neg() {
+        let mut tokens = TokenGen::from_code("a = -(2 - 5) * 2;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let stmt = tree.stmts().next().unwrap();
+        let mul = stmt.child(1).unwrap();
+        assert_eq!(mul.child(0).unwrap().ty(), Ntype::Neg);
+        assert_eq!(mul.child(0).unwrap().childs().len(), 1);
+
+        let mut var = std::collections::HashMap::new();
+        let mut mem = Vec::new();
+        tree.compile(&mut var, &mut mem).unwrap();
+        assert_eq!(mem[var[&Symbol::intern("a")]], 6);
+    }
+
+    #[test]
+    fn visit_and_fold() {
+        struct CountVars(usize);
+        impl<'a> Visitor<'a> for CountVars {
+            fn visit_lvar(&mut self, _node: Node<'a>) {
+                self.0 += 1;
+            }
+        }
+        struct Rename;
+        impl VisitorMut for Rename {
+            fn visit_lvar_mut(&mut self, ast: &mut Ast, id: NodeId) {
+                ast.set_value(id, DataUnion::str("x"));
+            }
+        }
+        struct Double;
+        impl Fold for Double {
+            fn fold_num(&mut self, node: Node<'_>, dst: &mut Ast) -> NodeId {
+                match node.value() {
+                    DataUnion::Num(n) => dst.number(n * 2, node.span()),
+                    _ => fold_childs(self, node, dst),
+                }
+            }
+        }
+
+        let mut tokens = TokenGen::from_code("a = 1 + -2; b = a * 3;").unwrap();
+        let mut tree = Program::from_tokens(&mut tokens).unwrap();
+        let mut count = CountVars(0);
+        tree.visit(&mut count);
+        assert_eq!(count.0, 3);
+
+        let doubled = tree.fold(&mut Double);
+        let mut var = std::collections::HashMap::new();
+        let mut mem = Vec::new();
+        doubled.compile(&mut var, &mut mem).unwrap();
+        assert_eq!(mem[var[&Symbol::intern("b")]], -12);
+
+        tree.visit_mut(&mut Rename);
+        let mut var = std::collections::HashMap::new();
+        let mut mem = Vec::new();
+        tree.compile(&mut var, &mut mem).unwrap();
+        assert_eq!(var.len(), 1);
+        assert_eq!(mem[var[&Symbol::intern("x")]], -3);
+    }
+
+    #[test]
+    fn lint_rules() {
+        let code = "a = 1; // lint: allow(dead-store)\nb = 1;\nb = b / (2 - 2);\n// lint: allow(no-effect)\na + b;\na * 2;\n";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+
+        let lints = Linter::with_config(&LintConfig::new())
+            .unwrap()
+            .run(&tree, &tokens);
+        let found: Vec<_> = lints.iter().map(|l| (l.rule, l.severity)).collect();
+        assert_eq!(
+            found,
+            [
+                ("div-by-zero", Severity::Error),
+                ("no-effect", Severity::Warning)
+            ]
+        );
+        assert!(check(&lints).is_err());
+
+        let mut config = LintConfig::new();
+        config.set("div-by-zero", Level::Allow);
+        config.set("no-effect", Level::Deny);
+        let lints = Linter::with_config(&config).unwrap().run(&tree, &tokens);
+        let found: Vec<_> = lints.iter().map(|l| (l.rule, l.severity)).collect();
+        assert_eq!(found, [("no-effect", Severity::Error)]);
+
+        config.set("no-such-rule", Level::Warn);
+        assert!(Linter::with_config(&config).is_err());
+
+        let code = concat!(
+            "c = c;\n",
+            "c = c; // lint: allow(self-assign)\n",
+            "d = 1;\n",
+            "d = 2;\n",
+            "e = 1; // lint: allow(dead-store)\n",
+            "e = 2;\n",
+            "f = d + e;\n",
+        );
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let lints = Linter::with_config(&LintConfig::new())
+            .unwrap()
+            .run(&tree, &tokens);
+        let mut found: Vec<_> = lints
+            .iter()
+            .map(|l| (l.rule, tokens.line_of(l.span.0)))
+            .collect();
+        found.sort();
+        // 2行目の自己代入と5行目の上書きされる代入は許可されている
+        assert_eq!(found, [("dead-store", 3), ("self-assign", 1)]);
+    }
+
+    #[test]
+    fn lower_netlist() {
+        let code = "count = count + 1; y = x * 2; y = y - x; z = (count == 3);";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        println!("{}", nl);
+
+        let names = |nets: &mut dyn Iterator<Item = NetId>| -> Vec<String> {
+            nets.map(|n| nl.net(n).name.unwrap().to_string()).collect()
+        };
+        assert_eq!(names(&mut nl.inputs().iter().copied()), ["x"]);
+        let outputs: Vec<_> = nl.outputs().iter().map(|o| o.0.to_string()).collect();
+        assert_eq!(outputs, ["count", "y", "z"]);
+        assert_eq!(nl.cells().filter(|(_, c)| c.kind.is_register()).count(), 3);
+        let z = nl.outputs()[2].1;
+        let d = nl.driver(z).unwrap().inputs[0];
+        assert_eq!(nl.driver(d).unwrap().kind, CellKind::Concat);
+        assert_eq!(nl.net(d).width, WORD);
+        // `x < y`は入力を入れ替えた`gt`になる
+        let nl = Netlist::from_source("l = x < y;").unwrap();
+        let (_, gt) = nl.cells().find(|(_, c)| c.kind == CellKind::Gt).unwrap();
+        let name = |n: NetId| nl.net(n).name.unwrap().to_string();
+        assert_eq!(
+            (name(gt.inputs[0]), name(gt.inputs[1])),
+            ("y".into(), "x".into())
+        );
+    }
+
+    #[test]
+    fn optimize_netlist() {
+        let code =
+            "a = 2 * 3 + x * 1; b = x + 0; c = (y - y) + a; d = x * 2 + x * 2; e = -(-a) / 1;";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        let (opt, reports) = optimize(&nl).unwrap();
+        for r in &reports {
+            println!("{}", r);
+        }
+        println!("{}", opt);
+
+        let count = |k: CellKind| opt.cells().filter(|(_, c)| c.kind == k).count();
+        assert_eq!(count(CellKind::Mul), 1);
+        assert_eq!(count(CellKind::Add), 2);
+        assert_eq!(
+            count(CellKind::Sub) + count(CellKind::Neg) + count(CellKind::Div),
+            0
+        );
+        assert_eq!(opt.cells().len(), 10);
+        assert_eq!(reports.last().unwrap().after, opt.cells().len());
+
+        let d_of = |name: &str| {
+            let q = opt
+                .outputs()
+                .iter()
+                .find(|o| o.0.as_str() == name)
+                .unwrap()
+                .1;
+            opt.driver(q).unwrap().inputs[0]
+        };
+        assert_eq!(d_of("b"), opt.inputs()[0]);
+        assert_eq!(d_of("c"), d_of("a"));
+        assert_eq!(d_of("e"), d_of("a"));
+
+        // simplify がセル数を変えずに x - x を定数にした後も、const-prop まで回る
+        let nl = Netlist::from_source("a = (x - x) > 3;").unwrap();
+        let (opt, reports) = optimize(&nl).unwrap();
+        assert!(reports.iter().any(|r| r.changed && r.before == r.after));
+        assert!(opt.cells().all(|(_, c)| c.kind != CellKind::Gt));
+        let q = opt.outputs()[0].1;
+        let d = opt.driver(q).unwrap().inputs[0];
+        assert_eq!(
+            opt.driver(d).unwrap().kind,
+            CellKind::Const {
+                value: 0,
+                width: WORD
+            }
+        );
+    }
+
+    #[test]
+    fn pass_manager() {
+        let mut pm = PassManager::standard();
+        assert!(pm.stop_after("typecheck").is_err());
+        pm.stop_after("lower").unwrap();
+        pm.dump_after("lint").unwrap();
+        let mut cx = Compilation::new(TokenGen::from_code("a = 1 + 2;").unwrap());
+        let stats = pm.run(&mut cx).unwrap();
+        let names: Vec<_> = stats.iter().map(|s| s.pass).collect();
+        assert_eq!(names, ["parse", "lint", "lower"]);
+        assert!(cx.netlist.is_some());
+        assert!(cx.opt_reports.is_empty());
+        assert_eq!(cx.dumps.len(), 1);
+        assert_eq!(cx.dumps[0].0, "lint");
+
+        // AIGは要るときだけ作る
+        let mut pm = PassManager::standard();
+        assert!(pm.pass_names().all(|p| p != "aig"));
+        let mut cx = Compilation::new(TokenGen::from_code("a = 1 + 2;").unwrap());
+        pm.run(&mut cx).unwrap();
+        assert!(cx.aig.is_none());
+        assert!(Compilation::needs_aig("blif") && !Compilation::needs_aig("verilog"));
+        pm.add(Box::new(Bitblast));
+        let mut cx = Compilation::new(TokenGen::from_code("a = 1 + 2;").unwrap());
+        pm.run(&mut cx).unwrap();
+        assert!(cx.aig.is_some());
+
+        let mut pm = PassManager::standard();
+        pm.retain(&["parse", "lower"]).unwrap();
+        let mut cx = Compilation::new(TokenGen::from_code("a = 1 / 0;").unwrap());
+        assert_eq!(pm.run(&mut cx).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn bitblast() {
+        let code = "s = x + y; d = x - y; m = x * y; q = x / y; n = -x;\n\
+                    e = (x == y); g = (x > y) + (x >= y) * 2 + (x < y) * 4; c = c + 1;";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        let aig = Aig::from_netlist(&nl).unwrap();
+        println!("{}", aig);
+        assert_eq!(aig.inputs().len(), 64);
+        assert_eq!(aig.latches().len(), 8 * 32);
+
+        let mut seed = 0x2545f491u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as i32
+        };
+        let mut state = aig.init_state();
+        let mut var = std::collections::HashMap::new();
+        let mut mem = Vec::new();
+        let (x, y) = (Symbol::intern("x"), Symbol::intern("y"));
+        var.insert(x, 0);
+        var.insert(y, 1);
+        mem.extend([0, 0]);
+        let cases = [(7, 3), (-7, 3), (i32::MIN, -1), (5, 5), (-1, i32::MAX)];
+        for i in 0..40 {
+            let (a, b) = cases
+                .get(i)
+                .copied()
+                .unwrap_or_else(|| (rand(), rand() >> (i % 31)));
+            let b = if b == 0 { 1 } else { b };
+            mem[var[&x]] = a;
+            mem[var[&y]] = b;
+            tree.compile(&mut var, &mut mem).unwrap();
+
+            let inputs: Vec<bool> = (0..64)
+                .map(|k| ([a, b][k / 32] >> (k % 32)) & 1 == 1)
+                .collect();
+            aig.step(&inputs, &mut state);
+            for (r, (_, name)) in aig.latches().iter().enumerate().step_by(32) {
+                let sym = Symbol::intern(name.trim_end_matches("[0]"));
+                let value = (0..32).fold(0u32, |v, k| v | (state[r + k] as u32) << k);
+                assert_eq!(value as i32, mem[var[&sym]], "{} with x={} y={}", sym, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn aiger_blif_roundtrip() {
+        let code = "c = c + x; d = (c > x) - x * 3; e = -d;";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let aig = Aig::from_netlist(&optimize(&Netlist::from_program(&tree).unwrap()).unwrap().0)
+            .unwrap();
+
+        let aag = aig.to_aag();
+        assert_eq!(Aig::from_aiger(aag.as_bytes()).unwrap().to_aag(), aag);
+        let bin = aig.to_aig();
+        assert_eq!(Aig::from_aiger(&bin).unwrap().to_aig(), bin);
+        assert_eq!(Aig::from_aiger(&bin).unwrap().to_aag(), aag);
+
+        let blif = Aig::from_blif(&aig.to_blif("top")).unwrap();
+        assert_eq!(blif.outputs().len(), aig.outputs().len());
+        let (mut s0, mut s1) = (aig.init_state(), blif.init_state());
+        for i in 0..20u32 {
+            let x = i.wrapping_mul(0x9e3779b9);
+            let inputs: Vec<bool> = (0..32).map(|k| (x >> k) & 1 == 1).collect();
+            assert_eq!(aig.step(&inputs, &mut s0), blif.step(&inputs, &mut s1));
+        }
+
+        // 合流する経路はループではない
+        let dag = ".model top\n.inputs x y\n.outputs a\n\
+                   .names x y d\n11 1\n.names d b1\n1 1\n.names d b2\n1 1\n\
+                   .names d b3\n1 1\n.names b1 b2 b3 c\n111 1\n\
+                   .names b1 b2 b3 c a\n1111 1\n.end\n";
+        let dag = Aig::from_blif(dag).unwrap();
+        let mut s = dag.init_state();
+        assert_eq!(dag.step(&[true, true], &mut s), [true]);
+        assert_eq!(dag.step(&[true, false], &mut s), [false]);
+        let looped = ".model top\n.inputs x\n.outputs a\n\
+                      .names x b a\n11 1\n.names a b\n1 1\n.end\n";
+        assert!(Aig::from_blif(looped).is_err());
+        assert!(Aig::from_aiger(b"aag 2 0 0 1 2\n2\n2 4 1\n4 2 1\n").is_err());
+        let ok = Aig::from_aiger(b"aag 4 1 0 1 3\n2\n8\n4 2 2\n6 4 4\n8 4 6\n").unwrap();
+        assert_eq!(ok.outputs().len(), 1);
+
+        // 壊れた入力はパニックせずにエラーになる
+        for bad in [
+            &b"aag 2 1 0 1 1\n2\n4\n\n"[..],
+            b"aag 2 1 0 1 1\n2\n4\n4 2\n",
+            b"aag 2 1 1 0 0\n2\n4\n",
+            b"aig 2 1 0 1 1\n4\n\x05\x00",
+            b"aig 2 1 0 1 1\n4\n\x01\x05",
+            b"aig 2 1 0 1 1\n4\n\xff\xff\xff\xff\xff\x01",
+            b"aag 1 1 0 0 1\n2\n",
+            b"aag\n",
+        ] {
+            assert!(Aig::from_aiger(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn verilog_backend() {
+        let code = "c = c + x; d = x / c; reg = (c > x);";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let v = Netlist::from_program(&tree).unwrap().to_verilog("counter");
+        println!("{}", v);
+        assert!(v.starts_with("module counter (\n  input wire clk,\n  input wire [31:0] x,\n"));
+        assert!(v.contains(
+            "  output reg [31:0] c,\n  output reg [31:0] d,\n  output reg [31:0] reg_0\n);"
+        ));
+        assert!(v.contains("assign c_0 = c + x;"));
+        assert!(v.contains("c_0 == 0 ? -32'sd1 : $signed(x) / $signed(c_0);"));
+        assert!(v.contains("    c <= c_0;\n"));
+        assert!(v.ends_with("endmodule\n"));
+
+        let v = Netlist::from_source("wait = wait + fork; supply = 1;")
+            .unwrap()
+            .to_verilog("top");
+        assert!(v.contains("  input wire [31:0] fork_0,\n"));
+        assert!(v.contains("  output reg [31:0] wait_0,\n  output reg [31:0] supply\n"));
+        assert!(v.contains("assign wait_1 = wait_0 + fork_0;"));
+    }
+
+    #[test]
+    fn pretty_print() {
+        let code = "// head\nb=7;   a = b ; // tail\n\n\na = (a == b)+(5);a=4;\n\
+                    x = -(-y) - (c - d) * -e / (f*g);  z = a < b < c;\n\
+                    w = ((a>=b) != (c<=d)) == (0);\n// end\n";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let printed = tree.to_source(Some(&tokens));
+        assert_eq!(
+            printed,
+            "// head\nb = 7;\na = b; // tail\n\na = (a == b) + 5;\na = 4;\n\
+             x = -(-y) - (c - d) * -e / (f * g);\nz = a < b < c;\n\
+             w = a >= b != c <= d == 0;\n// end\n"
+        );
+        let mut again = TokenGen::from_code(&printed).unwrap();
+        let reparsed = Program::from_tokens(&mut again).unwrap();
+        assert!(reparsed.same_tree(&tree));
+        assert_eq!(reparsed.to_source(Some(&again)), printed);
+    }
+
+    #[test]
+    fn dot_export() {
+        let mut tokens = TokenGen::from_code("c = c + x; d = c > 2;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph ast {"));
+        assert!(dot.contains("[label=\"+\", shape=ellipse];"));
+        assert_eq!(dot.matches("stmt ").count(), 2);
+        assert_eq!(
+            tree.stmts()
+                .nth(1)
+                .unwrap()
+                .to_dot()
+                .matches(" -> ")
+                .count(),
+            4
+        );
+
+        let dot = Netlist::from_program(&tree).unwrap().to_dot();
+        assert!(dot.contains("[label=\"x\", shape=invtriangle];"));
+        assert!(dot.contains("[label=\"c [32]\"];"));
+        assert!(dot.contains("[label=\"[1]\"];"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn json_export() {
+        let s = Json::obj([(
+            "a\"\n",
+            Json::Arr(vec![Json::Null, 3.into(), "\u{1}".into()]),
+        )]);
+        assert_eq!(s.to_string(), r#"{"a\"\n":[null,3,"\u0001"]}"#);
+
+        let tokens = TokenGen::from_code("x = -1; // c\n").unwrap();
+        assert_eq!(
+            super::json::tokens(&tokens).unwrap().to_string(),
+            concat!(
+                r#"{"version":1,"tokens":[{"kind":"Ident","value":"x","span":[0,1]},"#,
+                r#"{"kind":"Reserved","value":"=","span":[2,3]},"#,
+                r#"{"kind":"Reserved","value":"-","span":[4,5]},"#,
+                r#"{"kind":"Number","value":1,"span":[5,6]},"#,
+                r#"{"kind":"Reserved","value":";","span":[6,7]}],"comments":[[8,12]]}"#
+            )
+        );
+
+        let mut tokens = TokenGen::from_code("a = 1; a = 2;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        assert!(super::json::ast(&tree).to_string().starts_with(
+            r#"{"version":1,"statements":[{"id":2,"type":"Assign","value":null,"span":[0,5],"children":[{"id":0,"type":"Lvar","value":"a""#
+        ));
+        let lints = Linter::with_config(&LintConfig::new())
+            .unwrap()
+            .run(&tree, &tokens);
+        let diag = super::json::diagnostics(&lints, None, &tokens).to_string();
+        assert!(
+            diag.contains(r#""severity":"warning","code":"dead-store""#),
+            "{}",
+            diag
+        );
+
+        // 構文エラーはエラー自身の位置で報告する
+        for (code, span) in [
+            ("b = 1;\na + 1 = 2;", r#""span":[7,12],"line":2"#),
+            ("a = 1 + ;", r#""span":[8,9],"line":1"#),
+        ] {
+            let mut cx = Compilation::new(TokenGen::from_code(code).unwrap());
+            let e = PassManager::standard().run(&mut cx).err().unwrap();
+            let diag = cx.diagnostics(Some(&e));
+            assert!(diag.contains(span), "{}", diag);
+        }
+    }
+
+    #[test]
+    fn yosys_counter() {
+        let mut tokens = TokenGen::from_code("count = count + 1;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let json = Netlist::from_program(&tree)
+            .unwrap()
+            .to_yosys_json("counter");
+        let field = |j: &Json, key: &str| match j {
+            Json::Obj(v) => v.iter().find(|f| f.0 == key).unwrap().1.clone(),
+            _ => panic!("not an object"),
+        };
+        let bits = |j: &Json| match field(j, "bits") {
+            Json::Arr(b) => b,
+            _ => panic!("bits is not an array"),
+        };
+        let module = field(&field(&json, "modules"), "counter");
+        let ports = field(&module, "ports");
+        assert_eq!(field(&field(&ports, "clk"), "direction"), "input".into());
+        let count = field(&ports, "count");
+        assert_eq!(field(&count, "direction"), "output".into());
+        let q = bits(&count);
+        assert_eq!(q.len(), 32);
+        assert_eq!(bits(&field(&ports, "clk")), vec![Json::Num(2)]);
+
+        let Json::Obj(cells) = field(&module, "cells") else {
+            panic!("cells is not an object")
+        };
+        let types: Vec<_> = cells.iter().map(|c| field(&c.1, "type")).collect();
+        assert_eq!(types, vec!["$add".into(), "$dff".into()]);
+        let (add, dff) = (
+            field(&cells[0].1, "connections"),
+            field(&cells[1].1, "connections"),
+        );
+        // count + 1 の結果がレジスタに入り、レジスタの出力が加算に戻る
+        assert_eq!(field(&add, "A"), Json::Arr(q.clone()));
+        let mut one = vec![Json::from("0"); 32];
+        one[0] = "1".into();
+        assert_eq!(field(&add, "B"), Json::Arr(one));
+        assert_eq!(field(&dff, "D"), field(&add, "Y"));
+        assert_eq!(field(&dff, "Q"), Json::Arr(q));
+        assert_eq!(field(&dff, "CLK"), Json::Arr(vec![Json::Num(2)]));
+        let init = field(&field(&field(&module, "netnames"), "count"), "attributes");
+        assert_eq!(field(&init, "init"), Json::Str("0".repeat(32)));
+
+        // 入力 clk と暗黙のクロックは to_verilog と同じ名前に分かれる
+        let nl = Netlist::from_source("y = clk + 1;").unwrap();
+        let json = nl.to_yosys_json("top");
+        let module = field(&field(&json, "modules"), "top");
+        let Json::Obj(ports) = field(&module, "ports") else {
+            panic!("ports is not an object")
+        };
+        let names: Vec<_> = ports.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(names, ["clk_0", "clk", "y"]);
+        assert_eq!(bits(&ports[0].1), vec![Json::Num(2)]);
+        assert!(nl
+            .to_verilog("top")
+            .contains("  input wire clk_0,\n  input wire [31:0] clk,\n"));
+        let netnames = field(&module, "netnames");
+        assert_eq!(bits(&field(&netnames, "clk")), bits(&ports[1].1));
+    }
+
+    #[test]
+    fn vm_matches_tree_walker() {
+        let mut seed = 0x9e3779b9u32;
+        let mut rand = move |n: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % n
+        };
+        fn expr(rand: &mut dyn FnMut(u32) -> u32, depth: u32) -> String {
+            const VARS: [&str; 5] = ["a", "b", "c", "x", "y"];
+            const OPS: [&str; 10] = ["+", "-", "*", "/", "==", "!=", ">", ">=", "<", "<="];
+            match rand(if depth == 0 { 2 } else { 4 }) {
+                0 => VARS[rand(5) as usize].to_string(),
+                1 => rand(7).to_string(),
+                2 => format!("-({})", expr(rand, depth - 1)),
+                _ => format!(
+                    "({}) {} ({})",
+                    expr(rand, depth - 1),
+                    OPS[rand(10) as usize],
+                    expr(rand, depth - 1)
+                ),
+            }
+        }
+        for _ in 0..200 {
+            let mut code = String::new();
+            for _ in 0..1 + rand(5) {
+                let lhs = ["a", "b", "c"][rand(3) as usize];
+                code += &format!("{} = {};\n", lhs, expr(&mut rand, 4));
+            }
+            let mut tokens = TokenGen::from_code(&code).unwrap();
+            let tree = Program::from_tokens(&mut tokens).unwrap();
+            let mut vm = Vm::new(Code::compile(&tree).unwrap());
+            let mut var = std::collections::HashMap::new();
+            let mut mem = Vec::new();
+            for cycle in 0..10 {
+                let (x, y) = (rand(11) as i32 - 5, (rand(1 << 31) as i32).wrapping_mul(2));
+                for (name, v) in [("x", x), ("y", y)] {
+                    let s = Symbol::intern(name);
+                    vm.set(s, v);
+                    if let Some(&a) = var.get(&s) {
+                        mem[a] = v;
+                    } else {
+                        var.insert(s, mem.len());
+                        mem.push(v);
+                    }
+                }
+                let (r0, r1) = (tree.compile(&mut var, &mut mem), vm.run());
+                assert_eq!(r0.is_ok(), r1.is_ok(), "{}", code);
+                for (s, &a) in &var {
+                    if let Some(v) = vm.get(*s) {
+                        assert_eq!(mem[a], v, "{} in cycle {} of\n{}", s, cycle, code);
+                    }
+                }
+                if r0.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn event_sim() {
+        let code = "s = s + x; p = p * 3 - x; q = (s > p) + x / y; s = s - (q == 1);";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        let mut sim = EventSim::new(&nl, 10);
+        let (mut var, mut mem) = (std::collections::HashMap::new(), Vec::new());
+        let (x, y) = (Symbol::intern("x"), Symbol::intern("y"));
+        var.insert(x, 0);
+        var.insert(y, 1);
+        mem.extend([0, 0]);
+        for i in 0..30 {
+            let (a, b) = (i * 7 - 100, if i % 4 == 0 { -3 } else { i + 1 });
+            mem[0] = a;
+            mem[1] = b;
+            tree.compile(&mut var, &mut mem).unwrap();
+            assert!(sim.set(x, a as u32 as u64) && sim.set(y, b as u32 as u64));
+            sim.step().unwrap();
+            assert_eq!(sim.time(), (i as u64 + 1) * 10);
+            for name in ["s", "p", "q"] {
+                let sym = Symbol::intern(name);
+                assert_eq!(
+                    sim.get(sym).unwrap() as u32 as i32,
+                    mem[var[&sym]],
+                    "{}",
+                    name
+                );
+            }
+        }
+        assert!(sim.deltas() > 0 && sim.events() > 0);
+
+        // set の値はインアクティブ領域に入り、落ち着かせるまで見えない。幅で切り詰める。
+        let before = sim.get(x).unwrap();
+        assert!(sim.set(x, u64::MAX));
+        assert_eq!(sim.get(x), Some(before));
+        sim.settle().unwrap();
+        assert_eq!(sim.get(x), Some(0xffff_ffff));
+
+        // レジスタを通らない a = a + 1 はデルタサイクルの上限で止まる
+        let mut nl = Netlist::new();
+        let one = nl.add_cell(
+            CellKind::Const {
+                value: 1,
+                width: WORD,
+            },
+            &[],
+        );
+        let a = nl.add_net(WORD, Some(Symbol::intern("a")));
+        nl.connect(a, CellKind::Add, &[a, one]);
+        nl.add_output(Symbol::intern("a"), a);
+        let err = EventSim::new(&nl, 10).settle().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::SimulationError));
+        assert!(err.detail().unwrap().contains("does not settle"));
+        assert!(CycleSim::new(&nl).is_err());
+        let err = optimize(&nl).err().unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidData));
+        for pass in [const_prop, simplify, cse, dce] {
+            assert!(pass(&nl).is_err());
+        }
+        assert!(Aig::from_netlist(&nl).is_err());
+    }
+
+    #[test]
+    fn cycle_sim_matches_event_sim() {
+        let code = "s = s + x; p = p * 3 - x; q = (s > p) + x / y; s = s - (q == 1);";
+        let mut tokens = TokenGen::from_code(code).unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        for nl in [
+            Netlist::from_program(&tree).unwrap(),
+            optimize(&Netlist::from_program(&tree).unwrap()).unwrap().0,
+        ] {
+            let mut sims = [
+                Engine::parse("event").unwrap().build(&nl).unwrap(),
+                Engine::parse("cycle").unwrap().build(&nl).unwrap(),
+            ];
+            for i in 0..50u64 {
+                for sim in &mut sims {
+                    // 幅を超える上位ビットはどちらのシミュレータも捨てる
+                    sim.set(Symbol::intern("x"), i.wrapping_mul(0x9e3779b97f4a7c15));
+                    sim.set(Symbol::intern("y"), i % 3);
+                    sim.step().unwrap();
+                }
+                for (id, _) in nl.nets() {
+                    assert_eq!(
+                        sims[0].value(id),
+                        sims[1].value(id),
+                        "net %{} in cycle {}",
+                        id.index(),
+                        i
+                    );
+                }
+            }
+            assert_eq!(sims[1].cycle(), 50);
+        }
+        assert!(Engine::parse("fast").is_err());
+    }
+
+    #[test]
+    fn vcd_output() {
+        let mut tokens = TokenGen::from_code("count = count + x; big = count > 2;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        let run = |filter: &Filter| {
+            let mut sim = CycleSim::new(&nl).unwrap();
+            sim.set(Symbol::intern("x"), 1);
+            sim.settle().unwrap();
+            let mut rec = Recorder::new(&nl, Vec::new(), filter).unwrap();
+            rec.sample(&sim).unwrap();
+            for _ in 0..4 {
+                sim.step().unwrap();
+                rec.sample(&sim).unwrap();
+            }
+            String::from_utf8(rec.finish()).unwrap()
+        };
+        let all = run(&Filter::default());
+        assert!(
+            all.contains("$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n")
+        );
+        assert!(all.contains("$var reg 32 \" count [31:0] $end\n"));
+        assert!(all.contains("$scope module comb $end\n"));
+        assert!(all.contains("#0\n$dumpvars\n0!\n"));
+        assert!(all.contains("#5\n1!\nb11 \"\n"));
+        assert!(all.ends_with("#8\n0!\n"));
+
+        let only = run(&Filter {
+            signals: vec!["top.big".to_string()],
+            window: Some((4, 8)),
+            ..Filter::default()
+        });
+        assert!(!only.contains("count") && !only.contains("clk"));
+        // big は3サイクル目の立ち上がり(時刻5)で1になる
+        assert!(
+            only.ends_with("$enddefinitions $end\n#5\n$dumpvars\nb1 !\n$end\n"),
+            "{}",
+            only
+        );
+
+        let mut w = VcdWriter::new(Vec::new(), "1ps").unwrap();
+        let v = w.add_var("wire", 4, "v").unwrap();
+        w.end_definitions().unwrap();
+        w.time(0);
+        w.change(v, "x01z").unwrap();
+        w.change(v, "0011").unwrap();
+        // 4値でない値は書かずにエラーを返す
+        for bad in ["01a1", "", "1 0"] {
+            let e = w.change(v, bad).unwrap_err();
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        let text = String::from_utf8(w.into_inner()).unwrap();
+        assert!(text.ends_with("#0\nbx01z !\nb11 !\n"));
+
+        // 0の後のxやzは省かずに書き、読み直しても上位の0がxにならない
+        let mut w = VcdWriter::new(Vec::new(), "1ns").unwrap();
+        let v = w.add_var("wire", 4, "v").unwrap();
+        let u = w.add_var("wire", 8, "u").unwrap();
+        w.end_definitions().unwrap();
+        for (t, bits) in [(0, "0x01"), (1, "00z1"), (2, "0011"), (3, "0000")] {
+            w.time(t);
+            w.change(v, bits).unwrap();
+            w.change(u, &bits[1..]).unwrap();
+        }
+        let text = String::from_utf8(w.into_inner()).unwrap();
+        assert!(text.contains("#0\nb0x01 !\nb0x01 \"\n#1\nb0z1 !\n"));
+        let wave = Waveform::parse(&text).unwrap();
+        let (v, u) = (wave.signal("v").unwrap(), wave.signal("u").unwrap());
+        let values =
+            |s: &Signal| -> Vec<String> { s.changes.iter().map(|c| c.1.clone()).collect() };
+        assert_eq!(values(v), ["0x01", "00z1", "0011", "0000"]);
+        assert_eq!(values(u), ["00000x01", "000000z1", "00000011", "00000000"]);
+    }
+
+    #[test]
+    fn wave_diff() {
+        let mut tokens = TokenGen::from_code("count = count + x; big = count > 2;").unwrap();
+        let tree = Program::from_tokens(&mut tokens).unwrap();
+        let nl = Netlist::from_program(&tree).unwrap();
+        let run = |x: u64| {
+            let mut sim = CycleSim::new(&nl).unwrap();
+            sim.set(Symbol::intern("x"), x);
+            sim.settle().unwrap();
+            let mut rec = Recorder::new(&nl, Vec::new(), &Filter::default()).unwrap();
+            rec.sample(&sim).unwrap();
+            for _ in 0..4 {
+                sim.step().unwrap();
+                rec.sample(&sim).unwrap();
+            }
+            Waveform::parse(&String::from_utf8(rec.finish()).unwrap()).unwrap()
+        };
+        let one = run(1);
+        let count = one.signal("top.count").unwrap();
+        assert_eq!(count.width, 32);
+        assert_eq!(count.value_at(4), Some(format!("{:032b}", 2).as_str()));
+        assert_eq!(one.signal("top.clk").unwrap().value_at(5), Some("1"));
+        assert!(diff(&one, &run(1), &Default::default()).is_empty());
+
+        let two = run(2);
+        let d = diff(&one, &two, &Default::default());
+        assert!(d.contains(&Mismatch::Value {
+            path: "top.x".to_string(),
+            time: 0,
+            left: Some(format!("{:032b}", 1)),
+            right: Some(format!("{:032b}", 2)),
+        }));
+        // big は x=2 なら2サイクル目(時刻3)で先に1になる
+        assert!(d.iter().any(|m| matches!(m,
+            Mismatch::Value { path, time: 3, .. } if path == "top.big")));
+
+        let other = Waveform::parse(concat!(
+            "$timescale 1ns $end $scope module tb $end\n",
+            "$var wire 1 ! clock $end $var wire 4 # n [3:0] $end\n",
+            "$upscope $end $enddefinitions $end\n",
+            "#0 $dumpvars 0! bx # $end #1 1! b10 # #2 0!\n",
+        ))
+        .unwrap();
+        assert_eq!(other.signal("tb.n").unwrap().value_at(0), Some("xxxx"));
+        let mine = Waveform::parse(concat!(
+            "$timescale 1 ns $end\n",
+            "$scope module top $end $var wire 1 ! clk $end $var reg 8 \" m $end\n",
+            "$upscope $end $enddefinitions $end\n",
+            "#0 0! bx \" #1 1! b11 \" #2 0!\n",
+        ))
+        .unwrap();
+        let map = [("clk", "clock"), ("m", "tb.n")]
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect();
+        let d = diff(&mine, &other, &map);
+        assert_eq!(d.len(), 1);
+        assert_eq!(
+            d[0].to_string(),
+            "top.m: first mismatch at #1: 00000011 != 00000010"
+        );
+
+        let mut map = map;
+        map.insert("m".to_string(), "tb.nothing".to_string());
+        assert_eq!(
+            diff(&mine, &other, &map)[0],
+            Mismatch::Unmapped {
+                path: "top.m".to_string(),
+                target: "tb.nothing".to_string(),
+            }
+        );
+        let ps = Waveform {
+            timescale: "1ps".to_string(),
+            ..other.clone()
+        };
+        assert_eq!(
+            diff(&other, &ps, &Default::default()),
+            [Mismatch::Timescale {
+                left: "1ns".to_string(),
+                right: "1ps".to_string(),
+            }]
+        );
+        let header = "$var wire 1 ! a $end $enddefinitions $end #0 ";
+        for bad in ["é!", "2!", "bxy !", "b1"] {
+            assert!(
+                Waveform::parse(&format!("{}{}", header, bad)).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn testbench() {
+        let nl = Netlist::from_source("count = count + step; neg = 0 - count;").unwrap();
+        for engine in [Engine::Cycle, Engine::Event] {
+            let mut tb = Testbench::new(&nl, engine).unwrap();
+            tb.expect("count", 0);
+            tb.poke("step", 3).unwrap();
+            tb.step(4).unwrap();
+            tb.expect("count", 12);
+            assert_eq!(tb.peek("count").unwrap(), 12u64);
+            tb.poke("step", -5).unwrap();
+            tb.step(1).unwrap();
+            let neg = tb.peek("neg").unwrap();
+            // neg は同じサイクルで更新した count を読む
+            assert_eq!(neg.to_i64(), -7);
+            let count = tb.peek("count").unwrap();
+            assert_eq!(count, 7i64);
+            assert!(count.bit(0) && count.bit(2) && !count.bit(3));
+            assert_eq!(count.to_string(), format!("{:032b}", 7));
+            assert_eq!(tb.cycle(), 5);
+            assert!(tb.poke("count", 1).is_err());
+            assert!(tb.peek("nothing").is_err());
+        }
+    }
+
+    #[test]
+    fn debugger() {
+        let code = "count = count + 1;\nbig = count > 2;\n\ntotal = total + count;\n";
+        let mut dbg = Debugger::new(code).unwrap();
+        let text = |r: Reply| match r {
+            Reply::Text(s) => s,
+            Reply::Quit => panic!("quit"),
+        };
+        assert_eq!(dbg.break_line(2).unwrap(), 1);
+        assert!(dbg.break_line(3).is_err());
+        assert_eq!(
+            dbg.run_cycles(1).unwrap(),
+            Some(Stop::Line { id: 1, line: 2 })
+        );
+        // 止まった文からは同じブレークポイントで止まらずに進む
+        assert_eq!(dbg.step().unwrap(), None);
+        assert_eq!(dbg.value(Symbol::intern("big")), 0);
+        assert!(dbg.delete(1) && !dbg.delete(1));
+
+        let id = dbg.break_if("count == 10").unwrap();
+        assert_eq!(dbg.run_cycles(100).unwrap(), Some(Stop::Cond { id }));
+        assert_eq!((dbg.cycle(), dbg.pc()), (9, 1));
+        assert_eq!(dbg.eval("count * 2").unwrap(), 20);
+
+        let w = dbg.watch(Symbol::intern("total")).unwrap();
+        assert_eq!(
+            dbg.run_cycles(1).unwrap(),
+            Some(Stop::Watch {
+                id: w,
+                old: 45,
+                new: 55
+            })
+        );
+        dbg.delete(w);
+        assert_eq!(
+            text(dbg.execute("print/x total").unwrap()),
+            "total = 0x37\n"
+        );
+        assert_eq!(text(dbg.execute("p/b big").unwrap()), "big = 0b1\n");
+        assert_eq!(
+            text(dbg.execute("print/u 0 - 1").unwrap()),
+            "0 - 1 = 4294967295\n"
+        );
+
+        dbg.execute("force count 0 - 5").unwrap();
+        dbg.execute("cycle 3").unwrap();
+        assert_eq!(dbg.value(Symbol::intern("count")), -5);
+        assert_eq!(dbg.value(Symbol::intern("big")), 0);
+        assert!(text(dbg.execute("info").unwrap()).contains("forced count = -5"));
+        dbg.execute("release count").unwrap();
+        dbg.execute("step").unwrap();
+        assert_eq!(dbg.value(Symbol::intern("count")), -4);
+        assert_eq!(
+            text(dbg.execute("where").unwrap()),
+            "cycle 13, line 2: big = count > 2;\n"
+        );
+        assert!(dbg.execute("release count").is_err());
+        // 状態はVmのレジスタで、プログラムにない変数は書けない
+        assert!(dbg.execute("set nothing 1").is_err());
+        assert!(dbg.execute("watch nothing").is_err());
+        assert_eq!(dbg.eval("nothing + 1").unwrap(), 1);
+        dbg.execute("set total 7").unwrap();
+        assert_eq!(dbg.eval("total < count").unwrap(), 0);
+        assert!(dbg.execute("break if x = 1").is_err());
+        assert!(dbg.execute("frobnicate").is_err());
+        assert_eq!(dbg.execute("quit").unwrap(), Reply::Quit);
+    }
+
     #[test]
     fn ptr_test() {
         let bo = ary![10, 20, 30];
@@ -0,0 +1,8 @@
+mod config;
+mod linter;
+mod rule;
+mod rules;
+
+pub use config::{Level, LintConfig};
+pub use linter::{check, Lint, Linter};
+pub use rule::{Context, Rule};
@@ -0,0 +1,67 @@
+use crate::error::{Error, ErrorKind, Severity};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn severity(self) -> Option<Severity> {
+        match self {
+            Level::Allow => None,
+            Level::Warn => Some(Severity::Warning),
+            Level::Deny => Some(Severity::Error),
+        }
+    }
+}
+
+/// ルールごとのレベルの上書き設定
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<String, Level>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, rule: &str, level: Level) {
+        self.levels.insert(rule.to_string(), level);
+    }
+
+    pub fn get(&self, rule: &str) -> Option<Level> {
+        self.levels.get(rule).copied()
+    }
+
+    /// `-A rule`, `-W rule`, `-D rule`の形式のコマンドライン引数を読む。
+    /// 読んだ場合は`true`を返す。
+    pub fn parse_arg<I>(&mut self, flag: &str, args: &mut I) -> Result<bool, Error>
+    where
+        I: Iterator<Item = String>,
+    {
+        let level = match flag {
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            _ => return Ok(false),
+        };
+        match args.next() {
+            Some(rule) => {
+                self.set(&rule, level);
+                Ok(true)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("{} requires a rule name", flag),
+            )),
+        }
+    }
+
+    pub(super) fn rules(&self) -> impl Iterator<Item = &str> {
+        self.levels.keys().map(|s| s.as_str())
+    }
+}
@@ -0,0 +1,161 @@
+use super::{rules, Context, Level, LintConfig, Rule};
+use crate::ast::{walk_node, Node, Program, Visitor};
+use crate::error::{Error, ErrorKind, Severity};
+use crate::tokenize::TokenGen;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub span: (usize, usize),
+    pub msg: String,
+}
+
+impl Lint {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Lint(self.severity)
+    }
+
+    pub fn report(&self, src: &TokenGen) {
+        src.error_span(
+            format!("{}[{}]: {}", self.severity, self.rule, self.msg),
+            self.span,
+        );
+    }
+}
+
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Level)>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// 組み込みのルールをすべて登録し、`config`でレベルを上書きする
+    pub fn with_config(config: &LintConfig) -> Result<Self, Error> {
+        let mut linter = Self::new();
+        for rule in rules::builtin() {
+            linter.register(rule);
+        }
+        linter.configure(config)?;
+        Ok(linter)
+    }
+
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        let level = rule.default_level();
+        self.rules.push((rule, level));
+    }
+
+    pub fn configure(&mut self, config: &LintConfig) -> Result<(), Error> {
+        for id in config.rules() {
+            if !self.rules.iter().any(|(r, _)| r.id() == id) {
+                return Err(Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!("unknown lint rule `{}`", id),
+                ));
+            }
+        }
+        for (rule, level) in &mut self.rules {
+            if let Some(l) = config.get(rule.id()) {
+                *level = l;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = (&dyn Rule, Level)> {
+        self.rules.iter().map(|(r, l)| (r.as_ref(), *l))
+    }
+
+    pub fn run(&mut self, prog: &Program, src: &TokenGen) -> Vec<Lint> {
+        let allowed = inline_allows(src);
+        let mut lints = Vec::new();
+        for (rule, level) in &mut self.rules {
+            let severity = match level.severity() {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut cx = Context::default();
+            for stmt in prog.stmts() {
+                rule.check_stmt(stmt, &mut cx);
+                RuleVisitor {
+                    rule: rule.as_mut(),
+                    cx: &mut cx,
+                }
+                .visit_node(stmt);
+            }
+            rule.check_end(&mut cx);
+            for (span, msg) in cx.take() {
+                if allowed.contains(&(rule.id().to_string(), src.line_of(span.0))) {
+                    continue;
+                }
+                lints.push(Lint {
+                    rule: rule.id(),
+                    severity,
+                    span,
+                    msg,
+                });
+            }
+        }
+        lints.sort_by_key(|l| l.span);
+        lints
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// エラーレベルのリントがあれば`Err`を返す
+pub fn check(lints: &[Lint]) -> Result<(), Error> {
+    match lints.iter().find(|l| l.severity == Severity::Error) {
+        Some(l) => Err(Error::new(l.kind(), l.msg.clone())),
+        None => Ok(()),
+    }
+}
+
+/// 全てのノードについて`Rule::check_node`を呼ぶ
+struct RuleVisitor<'r> {
+    rule: &'r mut dyn Rule,
+    cx: &'r mut Context,
+}
+
+impl<'a> Visitor<'a> for RuleVisitor<'_> {
+    fn visit_node(&mut self, node: Node<'a>) {
+        self.rule.check_node(node, self.cx);
+        walk_node(self, node);
+    }
+}
+
+/// `// lint: allow(rule, ...)`コメントが抑制する(ルール, 行)の組。
+/// 行頭のコメントは次の行に、行末のコメントはその行に効く。
+fn inline_allows(src: &TokenGen) -> HashSet<(String, usize)> {
+    let mut allowed = HashSet::new();
+    for &span in src.comments() {
+        let text = src.text(span);
+        let body = text.trim_start_matches('/').trim();
+        let list = match body
+            .strip_prefix("lint:")
+            .map(str::trim)
+            .and_then(|s| s.strip_prefix("allow("))
+            .and_then(|s| s.strip_suffix(')'))
+        {
+            Some(l) => l,
+            None => continue,
+        };
+        let line = if src.starts_line(span.0) {
+            src.line_of(span.0) + 1
+        } else {
+            src.line_of(span.0)
+        };
+        for rule in list.split(',') {
+            allowed.insert((rule.trim().to_string(), line));
+        }
+    }
+    allowed
+}
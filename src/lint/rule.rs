@@ -0,0 +1,40 @@
+use super::Level;
+use crate::ast::Node;
+
+/// リントのルール。
+/// `Linter`が各文を先頭から順に走査し、それぞれのフックを呼び出す。
+pub trait Rule {
+    fn id(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn default_level(&self) -> Level;
+
+    /// 各文について一度呼ばれる
+    fn check_stmt(&mut self, _stmt: Node<'_>, _cx: &mut Context) {}
+
+    /// 全てのノードについて行きがけ順に呼ばれる
+    fn check_node(&mut self, _node: Node<'_>, _cx: &mut Context) {}
+
+    /// 全ての文を見終わった後に呼ばれる。状態を持つルールはここで状態を空にする。
+    fn check_end(&mut self, _cx: &mut Context) {}
+}
+
+/// ルールが見つけた問題を溜めておく
+#[derive(Default)]
+pub struct Context {
+    reports: Vec<((usize, usize), String)>,
+}
+
+impl Context {
+    pub fn report<S>(&mut self, span: (usize, usize), msg: S)
+    where
+        S: Into<String>,
+    {
+        self.reports.push((span, msg.into()));
+    }
+
+    pub(super) fn take(&mut self) -> Vec<((usize, usize), String)> {
+        std::mem::take(&mut self.reports)
+    }
+}
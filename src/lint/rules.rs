@@ -0,0 +1,170 @@
+use super::{Context, Level, Rule};
+use crate::ast::{Node, Ntype};
+use crate::datatype::DataUnion;
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+pub fn builtin() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NoEffect),
+        Box::new(SelfAssign),
+        Box::new(DivByZero),
+        Box::new(DeadStore::default()),
+    ]
+}
+
+fn var_name(node: Node<'_>) -> Option<Symbol> {
+    match (node.ty(), node.value()) {
+        (Ntype::Lvar, DataUnion::Symbol(s)) => Some(*s),
+        _ => None,
+    }
+}
+
+/// 変数を含まない式の値
+fn const_value(node: Node<'_>) -> Option<i32> {
+    if let (Ntype::Num, DataUnion::Num(n)) = (node.ty(), node.value()) {
+        return Some(*n);
+    }
+    if node.ty() == Ntype::Neg {
+        return const_value(node.child(0)?).map(i32::wrapping_neg);
+    }
+    let (l, r) = (const_value(node.child(0)?)?, const_value(node.child(1)?)?);
+    match node.ty() {
+        Ntype::Add => Some(l.wrapping_add(r)),
+        Ntype::Sub => Some(l.wrapping_sub(r)),
+        Ntype::Mul => Some(l.wrapping_mul(r)),
+        Ntype::Div => l.checked_div(r),
+        _ => None,
+    }
+}
+
+/// 代入以外の文は値が捨てられるだけで何もしない
+struct NoEffect;
+
+impl Rule for NoEffect {
+    fn id(&self) -> &'static str {
+        "no-effect"
+    }
+
+    fn description(&self) -> &'static str {
+        "statement is not an assignment and has no effect"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_stmt(&mut self, stmt: Node<'_>, cx: &mut Context) {
+        if stmt.ty() != Ntype::Assign {
+            cx.report(stmt.span(), "statement has no effect");
+        }
+    }
+}
+
+/// `a = a;`
+struct SelfAssign;
+
+impl Rule for SelfAssign {
+    fn id(&self) -> &'static str {
+        "self-assign"
+    }
+
+    fn description(&self) -> &'static str {
+        "variable is assigned to itself"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_node(&mut self, node: Node<'_>, cx: &mut Context) {
+        if node.ty() != Ntype::Assign {
+            return;
+        }
+        if let (Some(lhs), Some(rhs)) = (node.child(0), node.child(1)) {
+            if let (Some(l), Some(r)) = (var_name(lhs), var_name(rhs)) {
+                if r == l {
+                    cx.report(node.span(), format!("`{}` is assigned to itself", l));
+                }
+            }
+        }
+    }
+}
+
+/// 定数0による除算。インタプリタは必ず`DivisionByZero`で止まるが、ネットリストや
+/// Verilogでは-1になり、実行系によって結果が食い違うので既定で拒否する。
+struct DivByZero;
+
+impl Rule for DivByZero {
+    fn id(&self) -> &'static str {
+        "div-by-zero"
+    }
+
+    fn description(&self) -> &'static str {
+        "division by a constant zero"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Deny
+    }
+
+    fn check_node(&mut self, node: Node<'_>, cx: &mut Context) {
+        if node.ty() != Ntype::Div {
+            return;
+        }
+        if let Some(r) = node.child(1) {
+            if const_value(r) == Some(0) {
+                cx.report(node.span(), "division by zero");
+            }
+        }
+    }
+}
+
+/// 読まれる前に上書きされる代入
+#[derive(Default)]
+struct DeadStore {
+    pending: HashMap<Symbol, (usize, usize)>,
+}
+
+impl DeadStore {
+    fn reads(&mut self, node: Node<'_>) {
+        if let Some(name) = var_name(node) {
+            self.pending.remove(&name);
+        }
+        // 代入先は読まれない
+        let skip = (node.ty() == Ntype::Assign) as usize;
+        for ch in node.childs().skip(skip) {
+            self.reads(ch);
+        }
+    }
+}
+
+impl Rule for DeadStore {
+    fn id(&self) -> &'static str {
+        "dead-store"
+    }
+
+    fn description(&self) -> &'static str {
+        "assigned value is overwritten before it is read"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    fn check_stmt(&mut self, stmt: Node<'_>, cx: &mut Context) {
+        self.reads(stmt);
+        if stmt.ty() != Ntype::Assign {
+            return;
+        }
+        if let Some(name) = stmt.child(0).and_then(var_name) {
+            if let Some(span) = self.pending.insert(name, stmt.span()) {
+                cx.report(span, format!("value assigned to `{}` is never read", name));
+            }
+        }
+    }
+
+    fn check_end(&mut self, _cx: &mut Context) {
+        self.pending.clear();
+    }
+}
@@ -1,14 +1,340 @@
-mod ast;
-mod datatype;
-mod error;
-mod tokenize;
-
 //use error::Error;
-use ast::Program;
-use tokenize::TokenGen;
+use compiler::ast::Program;
+use compiler::debug::{Debugger, Reply};
+use compiler::error::{Error, ErrorKind};
+use compiler::netlist::{mask, sext, Netlist, WORD};
+use compiler::pass::{Bitblast, Compilation, CountingAlloc, PassManager};
+use compiler::sim::Engine;
+use compiler::symbol::Symbol;
+use compiler::tokenize::TokenGen;
+use compiler::vcd::{Filter, Recorder, Waveform};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
 
 fn main() {
-    let mut tokens = TokenGen::from_file("./code.txt".to_string()).unwrap();
-    let tree = Program::from_tokens(&mut tokens).unwrap();
-    println!("{:?}", tree);
+    let mut path = "./code.txt".to_string();
+    let mut lint = compiler::lint::LintConfig::new();
+    let mut passes = None;
+    let mut dump_after = Vec::new();
+    let mut stop_after = None;
+    let mut time_passes = false;
+    let mut emit = None;
+    let mut output = None;
+    let mut cycles = None;
+    let mut engine = "cycle".to_string();
+    let mut inputs = Vec::new();
+    let mut vcd = None;
+    let mut filter = Filter::default();
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if_eq("fmt").is_some() {
+        fmt(args);
+        return;
+    }
+    if args.next_if_eq("repl").is_some() {
+        repl(args.next().unwrap_or_else(|| "./code.txt".to_string()));
+        return;
+    }
+    if args.next_if_eq("wavediff").is_some() {
+        wavediff(args);
+        return;
+    }
+    while let Some(arg) = args.next() {
+        if lint.parse_arg(&arg, &mut args).unwrap_or_else(|e| fail(e)) {
+            continue;
+        }
+        match arg.as_str() {
+            "--passes" => passes = Some(value(&mut args, "--passes requires a list of passes")),
+            "--dump-after" => dump_after.push(value(&mut args, "--dump-after requires a pass")),
+            "--stop-after" => stop_after = Some(value(&mut args, "--stop-after requires a pass")),
+            "--time-passes" => time_passes = true,
+            "--emit" => emit = Some(value(&mut args, "--emit requires a format")),
+            "--simulate" => {
+                let n = value(&mut args, "--simulate requires a cycle count");
+                cycles = Some(number::<u64>(&n, "invalid cycle count"));
+            }
+            "--engine" => engine = value(&mut args, "--engine requires event or cycle"),
+            "--input" => {
+                let arg = value(&mut args, "--input requires name=value");
+                let (name, v) = split(&arg, '=', "--input requires name=value");
+                inputs.push((name.to_string(), number::<i64>(v, "invalid value")));
+            }
+            "--vcd" => vcd = Some(value(&mut args, "--vcd requires a path")),
+            "--vcd-scope" => filter
+                .scopes
+                .push(value(&mut args, "--vcd-scope requires a scope")),
+            "--vcd-signal" => filter
+                .signals
+                .push(value(&mut args, "--vcd-signal requires a signal")),
+            "--vcd-window" => {
+                let arg = value(&mut args, "--vcd-window requires start:end");
+                let (a, b) = split(&arg, ':', "--vcd-window requires start:end");
+                filter.window = Some((
+                    number(a, "invalid start time"),
+                    number(b, "invalid end time"),
+                ));
+            }
+            "-o" => output = Some(value(&mut args, "-o requires a path")),
+            _ => path = arg,
+        }
+    }
+
+    // AIGは出力に要るときか、パスの名前で指定されたときだけ作る
+    let mut pm = PassManager::standard();
+    let mut named = passes
+        .iter()
+        .flat_map(|p: &String| p.split(','))
+        .chain(dump_after.iter().map(|d| d.as_str()))
+        .chain(stop_after.as_deref());
+    if emit.as_deref().is_some_and(Compilation::needs_aig) || named.any(|n| n == "aig") {
+        pm.add(Box::new(Bitblast));
+    }
+    if let Some(names) = &passes {
+        pm.retain(&names.split(',').collect::<Vec<_>>())
+            .unwrap_or_else(|e| fail(e));
+    }
+    for name in &dump_after {
+        pm.dump_after(name).unwrap_or_else(|e| fail(e));
+    }
+    if let Some(name) = &stop_after {
+        pm.stop_after(name).unwrap_or_else(|e| fail(e));
+    }
+
+    let mut cx = Compilation::new(TokenGen::from_file(path).unwrap_or_else(|e| fail(e)));
+    cx.lint = lint;
+    let result = pm.run(&mut cx);
+    for (name, dump) in &cx.dumps {
+        println!("=== after {} ===\n{}", name, dump);
+    }
+    let stats = match result {
+        Ok(s) => s,
+        Err(e) => {
+            if emit.as_deref() == Some("json-diagnostics") {
+                write_output(output.as_deref(), cx.diagnostics(Some(&e)).as_bytes());
+                std::process::exit(1);
+            }
+            fail(e)
+        }
+    };
+    if let Some(format) = emit {
+        let bytes = cx.emit(&format).unwrap_or_else(|e| fail(e));
+        write_output(output.as_deref(), &bytes);
+    }
+    if let Some(n) = cycles {
+        let nl = cx.expect_netlist().unwrap_or_else(|e| fail(e));
+        let vcd = vcd.map(|path| {
+            let file = std::fs::File::create(path).unwrap_or_else(|e| fail(io_error(e)));
+            Recorder::new(nl, std::io::BufWriter::new(file), &filter)
+                .unwrap_or_else(|e| fail(io_error(e)))
+        });
+        if let Err(e) = simulate(nl, &engine, &inputs, n, vcd) {
+            fail(e);
+        }
+    }
+    if time_passes {
+        for s in &stats {
+            println!("{}", s);
+        }
+    }
+}
+
+/// オプションの値。なければ`msg`を出して終わる。
+fn value<I>(args: &mut I, msg: &str) -> String
+where
+    I: Iterator<Item = String>,
+{
+    args.next()
+        .unwrap_or_else(|| fail(Error::new(ErrorKind::InvalidArgument, msg)))
+}
+
+fn number<T: std::str::FromStr>(arg: &str, msg: &str) -> T {
+    arg.parse().unwrap_or_else(|_| {
+        fail(Error::new(
+            ErrorKind::InvalidArgument,
+            format!("{}: {}", msg, arg),
+        ))
+    })
+}
+
+fn split<'a>(arg: &'a str, sep: char, msg: &str) -> (&'a str, &'a str) {
+    arg.split_once(sep)
+        .unwrap_or_else(|| fail(Error::new(ErrorKind::InvalidArgument, msg)))
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(ErrorKind::CannotReadFile, e)
+}
+
+/// 入力を固定して`n`サイクル回し、毎サイクルの出力を表示する。
+/// `vcd`があれば波形も記録する。
+fn simulate<W: std::io::Write>(
+    nl: &Netlist,
+    engine: &str,
+    inputs: &[(String, i64)],
+    n: u64,
+    mut vcd: Option<Recorder<'_, W>>,
+) -> Result<(), Error> {
+    let mut sim = Engine::parse(engine)?.build(nl)?;
+    for (name, value) in inputs {
+        if !sim.set(Symbol::intern(name), *value as u64 & mask(WORD)) {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("no input named {}", name),
+            ));
+        }
+    }
+    sim.settle()?;
+    if let Some(r) = &mut vcd {
+        r.sample(sim.as_ref()).map_err(io_error)?;
+    }
+    for _ in 0..n {
+        sim.step()?;
+        if let Some(r) = &mut vcd {
+            r.sample(sim.as_ref()).map_err(io_error)?;
+        }
+        let values: Vec<String> = nl
+            .outputs()
+            .iter()
+            .map(|&(name, net)| format!("{}={}", name, sext(sim.value(net), nl.net(net).width)))
+            .collect();
+        println!("cycle {}: {}", sim.cycle(), values.join(" "));
+    }
+    if let Some(r) = vcd {
+        r.finish().flush().map_err(io_error)?;
+    }
+    Ok(())
+}
+
+/// エラーを詳しい説明と一緒に表示して終わる
+fn fail(e: Error) -> ! {
+    match e.detail() {
+        Some(d) => eprintln!("{}: {}", e, d),
+        None => eprintln!("{}", e),
+    }
+    std::process::exit(1)
+}
+
+/// `-o`で指定したファイルか標準出力に書く
+fn write_output(path: Option<&str>, bytes: &[u8]) {
+    match path {
+        Some(path) => std::fs::write(path, bytes),
+        None => std::io::Write::write_all(&mut std::io::stdout(), bytes),
+    }
+    .unwrap_or_else(|e| fail(io_error(e)));
+}
+
+/// `fmt [--check] files...`: ファイルを決まった書式に書き直す
+fn fmt<I>(args: I)
+where
+    I: Iterator<Item = String>,
+{
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("./code.txt".to_string());
+    }
+    let mut failed = false;
+    for path in paths {
+        let original = std::fs::read_to_string(&path).unwrap_or_else(|e| fail(io_error(e)));
+        let mut tokens = TokenGen::from_code(&original).unwrap_or_else(|e| fail(e));
+        let prog = match Program::from_tokens(&mut tokens) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let formatted = prog.to_source(Some(&tokens));
+
+        // 書き直しても意味が変わらないことを確かめてから書く
+        let mut again = TokenGen::from_code(&formatted).unwrap_or_else(|e| fail(e));
+        let same = Program::from_tokens(&mut again).is_ok_and(|p| p.same_tree(&prog))
+            && again.comments().len() == tokens.comments().len();
+        if !same {
+            eprintln!(
+                "{}: formatting would change the program; left untouched",
+                path
+            );
+            failed = true;
+        } else if formatted != original {
+            if check {
+                println!("{}: not formatted", path);
+                failed = true;
+            } else {
+                std::fs::write(&path, formatted).unwrap_or_else(|e| fail(io_error(e)));
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `wavediff [--map left=right]... a.vcd b.vcd`: 二つのVCDを信号ごとに比べ、
+/// 最初に食い違った時刻と値を表示する
+fn wavediff<I>(mut args: I)
+where
+    I: Iterator<Item = String>,
+{
+    let mut map = std::collections::HashMap::new();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => {
+                let arg = value(&mut args, "--map requires left=right");
+                let (a, b) = split(&arg, '=', "--map requires left=right");
+                map.insert(a.to_string(), b.to_string());
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [a, b] = &paths[..] else {
+        eprintln!("wavediff requires two VCD files");
+        std::process::exit(2);
+    };
+    let read = |path: &str| {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| fail(io_error(e)));
+        Waveform::parse(&text).unwrap_or_else(|e| fail(e))
+    };
+    let mismatches = compiler::vcd::diff(&read(a), &read(b), &map);
+    for m in &mismatches {
+        println!("{}", m);
+    }
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// `repl [path]`: 設計を読み込み、標準入力からデバッガのコマンドを読んで実行する
+fn repl(path: String) {
+    use std::io::{BufRead, Write};
+    let code = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| fail(Error::new(ErrorKind::CannotReadFile, e)));
+    let mut dbg = Debugger::new(&code).unwrap_or_else(|e| fail(e));
+    println!("loaded {}; type `help` for commands", path);
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("(rtl) ");
+        std::io::stdout()
+            .flush()
+            .unwrap_or_else(|e| fail(io_error(e)));
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        match dbg.execute(&line.unwrap_or_else(|e| fail(io_error(e)))) {
+            Ok(Reply::Text(s)) => print!("{}", s),
+            Ok(Reply::Quit) => break,
+            Err(e) => match e.detail() {
+                Some(d) => eprintln!("{}: {}", e, d),
+                None => eprintln!("{}", e),
+            },
+        }
+    }
 }
@@ -0,0 +1,185 @@
+mod cell;
+mod dot;
+mod lower;
+mod net;
+mod opt;
+mod verilog;
+mod yosys;
+
+pub use cell::{mask, sext, Cell, CellId, CellKind};
+pub use lower::WORD;
+pub use net::{Net, NetId};
+pub use opt::{const_prop, cse, dce, optimize, simplify, PassReport};
+
+use crate::symbol::Symbol;
+
+/// ワード単位のネットリスト。
+///
+/// セルの出力はそれぞれ一本のネットを駆動する。値はすべて2の補数で、
+/// 算術は`Program::compile`と同じく幅で切り捨てる。
+/// レジスタ(`CellKind::Dff`)は暗黙の一本のクロックで一斉に更新される。
+#[derive(Clone, Default)]
+pub struct Netlist {
+    nets: Vec<Net>,
+    cells: Vec<Cell>,
+    inputs: Vec<NetId>,
+    outputs: Vec<(Symbol, NetId)>,
+}
+
+impl Netlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_net(&mut self, width: u32, name: Option<Symbol>) -> NetId {
+        let id = NetId(self.nets.len() as u32);
+        self.nets.push(Net {
+            width,
+            name,
+            driver: None,
+        });
+        id
+    }
+
+    pub fn add_input(&mut self, name: Symbol, width: u32) -> NetId {
+        let net = self.add_net(width, Some(name));
+        self.inputs.push(net);
+        net
+    }
+
+    pub fn add_output(&mut self, name: Symbol, net: NetId) {
+        self.outputs.push((name, net));
+    }
+
+    /// 新しいネットを駆動するセルを追加し、そのネットを返す
+    pub fn add_cell(&mut self, kind: CellKind, inputs: &[NetId]) -> NetId {
+        let width = kind.width(inputs.iter().map(|&n| self.net(n).width));
+        let output = self.add_net(width, None);
+        self.connect(output, kind, inputs);
+        output
+    }
+
+    /// 既にあるネット`output`を駆動するセルを追加する
+    pub fn connect(&mut self, output: NetId, kind: CellKind, inputs: &[NetId]) -> CellId {
+        let id = CellId(self.cells.len() as u32);
+        assert!(self.net(output).driver.is_none(), "net has two drivers");
+        self.nets[output.index()].driver = Some(id);
+        self.cells.push(Cell {
+            kind,
+            inputs: inputs.to_vec(),
+            output,
+        });
+        id
+    }
+
+    pub fn set_name(&mut self, net: NetId, name: Symbol) {
+        self.nets[net.index()].name = Some(name);
+    }
+
+    pub fn net(&self, id: NetId) -> &Net {
+        &self.nets[id.index()]
+    }
+
+    pub fn cell(&self, id: CellId) -> &Cell {
+        &self.cells[id.index()]
+    }
+
+    pub fn nets(&self) -> impl ExactSizeIterator<Item = (NetId, &Net)> {
+        self.nets
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (NetId(i as u32), n))
+    }
+
+    pub fn cells(&self) -> impl ExactSizeIterator<Item = (CellId, &Cell)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (CellId(i as u32), c))
+    }
+
+    pub fn driver(&self, net: NetId) -> Option<&Cell> {
+        self.net(net).driver.map(|c| self.cell(c))
+    }
+
+    pub fn inputs(&self) -> &[NetId] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[(Symbol, NetId)] {
+        &self.outputs
+    }
+
+    /// レジスタ以外のセルを、入力を駆動するセルが先に来る順に並べる。
+    /// レジスタを通らないループがあれば`None`。
+    pub fn topo_order(&self) -> Option<Vec<CellId>> {
+        // 0: 未訪問, 1: 訪問中, 2: 済み
+        let mut state = vec![0u8; self.cells.len()];
+        let mut order = Vec::new();
+        for (id, cell) in self.cells() {
+            if cell.kind.is_register() || state[id.index()] != 0 {
+                continue;
+            }
+            let mut stack = vec![(id, 0)];
+            state[id.index()] = 1;
+            while let Some((c, i)) = stack.pop() {
+                match self.cell(c).inputs.get(i) {
+                    Some(&input) => {
+                        stack.push((c, i + 1));
+                        if let Some(d) = self.net(input).driver {
+                            if self.cell(d).kind.is_register() {
+                                continue;
+                            }
+                            match state[d.index()] {
+                                0 => {
+                                    state[d.index()] = 1;
+                                    stack.push((d, 0));
+                                }
+                                1 => return None,
+                                _ => {}
+                            }
+                        }
+                    }
+                    None => {
+                        state[c.index()] = 2;
+                        order.push(c);
+                    }
+                }
+            }
+        }
+        Some(order)
+    }
+
+    pub fn registers(&self) -> impl Iterator<Item = (CellId, &Cell)> {
+        self.cells().filter(|(_, c)| c.kind.is_register())
+    }
+}
+
+impl std::fmt::Display for Netlist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &i in &self.inputs {
+            writeln!(f, "input {}", self.net_label(i))?;
+        }
+        for (_, cell) in self.cells() {
+            write!(f, "{} = {}", self.net_label(cell.output), cell.kind)?;
+            for (i, n) in cell.inputs.iter().enumerate() {
+                write!(f, "{}%{}", if i == 0 { " " } else { ", " }, n.index())?;
+            }
+            writeln!(f)?;
+        }
+        for (name, n) in &self.outputs {
+            writeln!(f, "output {} = %{}", name, n.index())?;
+        }
+        Ok(())
+    }
+}
+
+impl Netlist {
+    fn net_label(&self, id: NetId) -> String {
+        let net = self.net(id);
+        match net.name {
+            Some(name) => format!("%{} {} [{}]", id.index(), name, net.width),
+            None => format!("%{} [{}]", id.index(), net.width),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::NetId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellId(pub(super) u32);
+
+impl CellId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellKind {
+    /// 幅`width`の定数。値は下位ビットから詰める。
+    Const {
+        value: u64,
+        width: u32,
+    },
+    Add,
+    Sub,
+    Mul,
+    /// 符号付きの除算。0で割った結果はすべて1(-1)とする。
+    Div,
+    Neg,
+    /// 比較の結果は1ビット。大小比較は符号付き。
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    /// 入力を上位から順に連結する
+    Concat,
+    /// 暗黙のクロックで入力を取り込むレジスタ
+    Dff {
+        init: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub kind: CellKind,
+    pub inputs: Vec<NetId>,
+    pub output: NetId,
+}
+
+impl CellKind {
+    /// 入力の幅から出力の幅を決める
+    pub fn width<I>(self, mut inputs: I) -> u32
+    where
+        I: Iterator<Item = u32>,
+    {
+        match self {
+            CellKind::Const { width, .. } => width,
+            CellKind::Eq | CellKind::Ne | CellKind::Gt | CellKind::Ge => 1,
+            CellKind::Concat => inputs.sum(),
+            _ => inputs.next().unwrap_or(0),
+        }
+    }
+
+    pub fn is_register(self) -> bool {
+        matches!(self, CellKind::Dff { .. })
+    }
+
+    /// 入力が交換できる演算か
+    pub fn is_commutative(self) -> bool {
+        matches!(
+            self,
+            CellKind::Add | CellKind::Mul | CellKind::Eq | CellKind::Ne
+        )
+    }
+
+    /// 入力の値と幅`(value, width)`から出力の値を計算する。
+    /// レジスタは入力をそのまま返す。
+    pub fn eval(self, args: &[(u64, u32)]) -> u64 {
+        let width = self.width(args.iter().map(|a| a.1));
+        let arg = |i: usize| args[i].0 & mask(args[i].1);
+        let sarg = |i: usize| sext(args[i].0, args[i].1);
+        let value = match self {
+            CellKind::Const { value, .. } => value,
+            CellKind::Add => arg(0).wrapping_add(arg(1)),
+            CellKind::Sub => arg(0).wrapping_sub(arg(1)),
+            CellKind::Mul => arg(0).wrapping_mul(arg(1)),
+            CellKind::Div => match sarg(1) {
+                0 => u64::MAX,
+                b => sarg(0).wrapping_div(b) as u64,
+            },
+            CellKind::Neg => arg(0).wrapping_neg(),
+            CellKind::Eq => (arg(0) == arg(1)) as u64,
+            CellKind::Ne => (arg(0) != arg(1)) as u64,
+            CellKind::Gt => (sarg(0) > sarg(1)) as u64,
+            CellKind::Ge => (sarg(0) >= sarg(1)) as u64,
+            CellKind::Concat => args.iter().fold(0u64, |acc, &(v, w)| {
+                acc.checked_shl(w).unwrap_or(0) | (v & mask(w))
+            }),
+            CellKind::Dff { .. } => arg(0),
+        };
+        value & mask(width)
+    }
+}
+
+/// 下位`width`ビットがすべて1の値
+pub fn mask(width: u32) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// 幅`width`の値を符号拡張する
+pub fn sext(value: u64, width: u32) -> i64 {
+    if width == 0 || width >= 64 {
+        value as i64
+    } else {
+        let shift = 64 - width;
+        ((value << shift) as i64) >> shift
+    }
+}
+
+impl std::fmt::Display for CellKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellKind::Const { value, .. } => write!(f, "const {}", value),
+            CellKind::Add => f.write_str("add"),
+            CellKind::Sub => f.write_str("sub"),
+            CellKind::Mul => f.write_str("mul"),
+            CellKind::Div => f.write_str("div"),
+            CellKind::Neg => f.write_str("neg"),
+            CellKind::Eq => f.write_str("eq"),
+            CellKind::Ne => f.write_str("ne"),
+            CellKind::Gt => f.write_str("gt"),
+            CellKind::Ge => f.write_str("ge"),
+            CellKind::Concat => f.write_str("concat"),
+            CellKind::Dff { init } => write!(f, "dff init {}", init),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::{CellKind, NetId, Netlist};
+
+impl Netlist {
+    /// セルをノード、ネットを辺にしたGraphvizのDOT。辺には名前と幅を書く。
+    pub fn to_dot(&self) -> String {
+        let mut s = "digraph netlist {\n  rankdir=LR;\n".to_string();
+        for &i in &self.inputs {
+            s += &format!(
+                "  i{} [label=\"{}\", shape=invtriangle];\n",
+                i.index(),
+                self.net(i).name.map_or("?", |n| n.as_str())
+            );
+        }
+        for (id, cell) in self.cells() {
+            let shape = match cell.kind {
+                CellKind::Dff { .. } => "box3d",
+                CellKind::Const { .. } => "plaintext",
+                _ => "box",
+            };
+            s += &format!(
+                "  c{} [label=\"{}\", shape={}];\n",
+                id.index(),
+                cell.kind,
+                shape
+            );
+            for &n in &cell.inputs {
+                s += &format!(
+                    "  {} -> c{} [label=\"{}\"];\n",
+                    self.dot_source(n),
+                    id.index(),
+                    self.edge_label(n)
+                );
+            }
+        }
+        for (k, (name, n)) in self.outputs.iter().enumerate() {
+            s += &format!("  o{} [label=\"{}\", shape=triangle];\n", k, name);
+            s += &format!(
+                "  {} -> o{} [label=\"{}\"];\n",
+                self.dot_source(*n),
+                k,
+                self.edge_label(*n)
+            );
+        }
+        s + "}\n"
+    }
+
+    /// ネットを駆動するノードの名前
+    fn dot_source(&self, net: NetId) -> String {
+        match self.net(net).driver {
+            Some(c) => format!("c{}", c.index()),
+            None => format!("i{}", net.index()),
+        }
+    }
+
+    fn edge_label(&self, net: NetId) -> String {
+        let n = self.net(net);
+        match n.name {
+            Some(name) => format!("{} [{}]", name, n.width),
+            None => format!("[{}]", n.width),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use super::{CellKind, NetId, Netlist};
+use crate::ast::{walk_childs, Node, Ntype, Program, Visitor};
+use crate::datatype::DataUnion;
+use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
+use crate::tokenize::TokenGen;
+use std::collections::HashMap;
+
+/// 変数の幅。言語の値はすべて`i32`。
+pub const WORD: u32 = 32;
+
+impl Netlist {
+    /// プログラム全体を一本のクロックで動く一つのブロックとしてネットリストにする。
+    ///
+    /// `Program::compile`を一回呼ぶことがクロック一回に当たる。
+    /// 代入される変数はすべてレジスタになり、その出力がそのまま外部出力になる。
+    /// 代入されずに読まれるだけの変数は外部入力になる。
+    pub fn from_program(prog: &Program) -> Result<Self, Error> {
+        let mut lower = Lower {
+            nl: Netlist::new(),
+            env: HashMap::new(),
+            values: Vec::new(),
+            error: None,
+        };
+        let mut regs = Vec::new();
+        for stmt in prog.stmts() {
+            if let Some(name) = assign_target(stmt) {
+                if !lower.env.contains_key(&name) {
+                    let q = lower.nl.add_net(WORD, Some(name));
+                    lower.env.insert(name, q);
+                    regs.push((name, q));
+                }
+            }
+        }
+        for stmt in prog.stmts() {
+            // 代入でない文は値を捨てるだけなので回路にならない
+            if stmt.ty() == Ntype::Assign {
+                lower.expr(stmt)?;
+            }
+        }
+        for (name, q) in regs {
+            let d = lower.env[&name];
+            lower.nl.connect(q, CellKind::Dff { init: 0 }, &[d]);
+            lower.nl.add_output(name, q);
+        }
+        Ok(lower.nl)
+    }
+
+    /// ソースを構文解析してネットリストにする
+    pub fn from_source(code: &str) -> Result<Self, Error> {
+        let mut tokens = TokenGen::from_code(code)?;
+        Self::from_program(&Program::from_tokens(&mut tokens)?)
+    }
+}
+
+fn assign_target(stmt: Node<'_>) -> Option<Symbol> {
+    match (stmt.ty(), stmt.child(0).map(|n| n.value())) {
+        (Ntype::Assign, Some(DataUnion::Symbol(s))) => Some(*s),
+        _ => None,
+    }
+}
+
+/// 各式の値のネットを`values`に積みながら回路を作る
+struct Lower {
+    nl: Netlist,
+    /// 各変数の現在の値を持つネット
+    env: HashMap<Symbol, NetId>,
+    values: Vec<NetId>,
+    /// 最初に起きたエラー。起きた後は何も作らない。
+    error: Option<Error>,
+}
+
+impl Lower {
+    fn expr(&mut self, node: Node<'_>) -> Result<NetId, Error> {
+        self.visit_node(node);
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(self.values.pop().unwrap()),
+        }
+    }
+
+    fn fail(&mut self, e: Error) {
+        self.error.get_or_insert(e);
+    }
+
+    fn cell(&mut self, node: Node<'_>, kind: CellKind) {
+        walk_childs(self, node);
+        self.finish(node, kind);
+    }
+
+    /// `a < b`は`b > a`のように左右を入れ替えたセルにする
+    fn swapped(&mut self, node: Node<'_>, kind: CellKind) {
+        walk_childs(self, node);
+        let n = self.values.len();
+        if self.error.is_none() && n >= 2 {
+            self.values.swap(n - 2, n - 1);
+        }
+        self.finish(node, kind);
+    }
+
+    /// 子の値を入力にしてセルを作る
+    fn finish(&mut self, node: Node<'_>, kind: CellKind) {
+        if self.error.is_some() {
+            return;
+        }
+        let inputs = self
+            .values
+            .split_off(self.values.len() - node.childs().len());
+        let out = self.nl.add_cell(kind, &inputs);
+        let width = self.nl.net(out).width;
+        let value = if width < WORD {
+            // 比較の結果は0か1の整数として扱う
+            let zero = self.constant(0, WORD - width);
+            self.nl.add_cell(CellKind::Concat, &[zero, out])
+        } else {
+            out
+        };
+        self.values.push(value);
+    }
+
+    fn constant(&mut self, value: u64, width: u32) -> NetId {
+        self.nl.add_cell(CellKind::Const { value, width }, &[])
+    }
+}
+
+impl<'a> Visitor<'a> for Lower {
+    fn visit_num(&mut self, node: Node<'a>) {
+        match node.value() {
+            DataUnion::Num(n) => {
+                let c = self.constant(*n as u32 as u64, WORD);
+                self.values.push(c);
+            }
+            _ => self.fail(Error::new(ErrorKind::UnexpectedError, "unexpected error.")),
+        }
+    }
+
+    fn visit_lvar(&mut self, node: Node<'a>) {
+        match node.value() {
+            DataUnion::Symbol(s) => {
+                let n = match self.env.get(s) {
+                    Some(&n) => n,
+                    None => {
+                        let n = self.nl.add_input(*s, WORD);
+                        self.env.insert(*s, n);
+                        n
+                    }
+                };
+                self.values.push(n);
+            }
+            _ => self.fail(Error::new(ErrorKind::UnexpectedError, "unexpected error.")),
+        }
+    }
+
+    fn visit_assign(&mut self, node: Node<'a>) {
+        let (Some(target), Some(rhs)) = (assign_target(node), node.child(1)) else {
+            self.fail(Error::new(ErrorKind::InvalidLvalue, "左辺値ではありません"));
+            return;
+        };
+        self.visit_node(rhs);
+        if self.error.is_some() {
+            return;
+        }
+        let value = *self.values.last().unwrap();
+        if self.nl.net(value).name.is_none() {
+            self.nl.set_name(value, target);
+        }
+        self.env.insert(target, value);
+    }
+
+    fn visit_add(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Add);
+    }
+
+    fn visit_sub(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Sub);
+    }
+
+    fn visit_mul(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Mul);
+    }
+
+    fn visit_div(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Div);
+    }
+
+    fn visit_neg(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Neg);
+    }
+
+    fn visit_eq(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Eq);
+    }
+
+    fn visit_neq(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Ne);
+    }
+
+    fn visit_greater(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Gt);
+    }
+
+    fn visit_greater_eq(&mut self, node: Node<'a>) {
+        self.cell(node, CellKind::Ge);
+    }
+
+    fn visit_less(&mut self, node: Node<'a>) {
+        self.swapped(node, CellKind::Gt);
+    }
+
+    fn visit_less_eq(&mut self, node: Node<'a>) {
+        self.swapped(node, CellKind::Ge);
+    }
+}
@@ -0,0 +1,19 @@
+use super::CellId;
+use crate::symbol::Symbol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NetId(pub(super) u32);
+
+impl NetId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub width: u32,
+    /// 元の変数名。一時的な値には名前がない。
+    pub name: Option<Symbol>,
+    pub(super) driver: Option<CellId>,
+}
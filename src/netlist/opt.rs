@@ -0,0 +1,220 @@
+use super::{mask, CellKind, NetId, Netlist};
+use crate::error::{Error, ErrorKind};
+use std::collections::{HashMap, HashSet};
+
+/// 一つのパスの前後のセル数と、セルを書き換えたかどうか
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub pass: &'static str,
+    pub before: usize,
+    pub after: usize,
+    pub changed: bool,
+}
+
+impl std::fmt::Display for PassReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {} cells", self.pass, self.before, self.after)
+    }
+}
+
+type Pass = fn(&Netlist) -> Result<(Netlist, bool), Error>;
+
+/// どのパスも書き換えなくなるまで(最大`MAX_ROUNDS`回)すべてのパスを順に適用する。
+/// セル数が同じでも中身が変わることがあるので、各パスの報告する変化を見る。
+/// レジスタを通らないループがあるとエラー。
+pub fn optimize(nl: &Netlist) -> Result<(Netlist, Vec<PassReport>), Error> {
+    const MAX_ROUNDS: usize = 8;
+    const PASSES: &[(&str, Pass)] = &[
+        ("const-prop", const_prop),
+        ("simplify", simplify),
+        ("cse", cse),
+        ("dce", dce),
+    ];
+    let mut nl = nl.clone();
+    let mut reports = Vec::new();
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        for &(pass, f) in PASSES {
+            let before = nl.cells.len();
+            let (next, rewrote) = f(&nl)?;
+            changed |= rewrote;
+            reports.push(PassReport {
+                pass,
+                before,
+                after: next.cells.len(),
+                changed: rewrote,
+            });
+            nl = next;
+        }
+        if !changed {
+            break;
+        }
+    }
+    Ok((nl, reports))
+}
+
+/// 入力がすべて定数のセルを定数に置き換える。書き換えたかどうかも返す。
+pub fn const_prop(nl: &Netlist) -> Result<(Netlist, bool), Error> {
+    let mut changed = false;
+    let nl = rebuild(
+        nl,
+        |_, _| true,
+        |dst, kind, inputs| {
+            if kind.is_register() || matches!(kind, CellKind::Const { .. }) {
+                return dst.add_cell(kind, inputs);
+            }
+            let args: Option<Vec<_>> = inputs
+                .iter()
+                .map(|&n| const_of(dst, n).map(|v| (v, dst.net(n).width)))
+                .collect();
+            match args {
+                Some(args) => {
+                    changed = true;
+                    let width = kind.width(args.iter().map(|a| a.1));
+                    let value = kind.eval(&args);
+                    dst.add_cell(CellKind::Const { value, width }, &[])
+                }
+                None => dst.add_cell(kind, inputs),
+            }
+        },
+    )?;
+    Ok((nl, changed))
+}
+
+/// `x+0`や`x*1`のような自明な演算を取り除く。書き換えたかどうかも返す。
+pub fn simplify(nl: &Netlist) -> Result<(Netlist, bool), Error> {
+    let mut changed = false;
+    let nl = rebuild(
+        nl,
+        |_, _| true,
+        |dst, kind, inputs| {
+            let c = |i: usize| const_of(dst, inputs[i]);
+            let same = inputs.len() == 2 && inputs[0] == inputs[1];
+            let width = kind.width(inputs.iter().map(|&n| dst.net(n).width));
+            let zero = |dst: &mut Netlist| dst.add_cell(CellKind::Const { value: 0, width }, &[]);
+            let out = match kind {
+                CellKind::Add if c(1) == Some(0) => inputs[0],
+                CellKind::Add if c(0) == Some(0) => inputs[1],
+                CellKind::Sub if c(1) == Some(0) => inputs[0],
+                CellKind::Sub if same => zero(dst),
+                CellKind::Mul if c(1) == Some(1) => inputs[0],
+                CellKind::Mul if c(0) == Some(1) => inputs[1],
+                CellKind::Mul if c(0) == Some(0) || c(1) == Some(0) => zero(dst),
+                CellKind::Div if c(1) == Some(1) => inputs[0],
+                CellKind::Neg if dst.driver(inputs[0]).map(|d| d.kind) == Some(CellKind::Neg) => {
+                    dst.driver(inputs[0]).unwrap().inputs[0]
+                }
+                CellKind::Eq | CellKind::Ge if same => {
+                    dst.add_cell(CellKind::Const { value: 1, width }, &[])
+                }
+                CellKind::Ne | CellKind::Gt if same => zero(dst),
+                _ => return dst.add_cell(kind, inputs),
+            };
+            changed = true;
+            out
+        },
+    )?;
+    Ok((nl, changed))
+}
+
+/// 同じ種類で同じ入力を持つセルを一つにまとめる。まとめたかどうかも返す。
+pub fn cse(nl: &Netlist) -> Result<(Netlist, bool), Error> {
+    let mut seen: HashMap<(CellKind, Vec<NetId>), NetId> = HashMap::new();
+    let mut changed = false;
+    let nl = rebuild(
+        nl,
+        |_, _| true,
+        |dst, kind, inputs| {
+            let mut key = inputs.to_vec();
+            if kind.is_commutative() {
+                key.sort();
+            }
+            if kind.is_register() {
+                return dst.add_cell(kind, inputs);
+            }
+            let key = (kind, key);
+            if let Some(&net) = seen.get(&key) {
+                changed = true;
+                return net;
+            }
+            let net = dst.add_cell(kind, inputs);
+            seen.insert(key, net);
+            net
+        },
+    )?;
+    Ok((nl, changed))
+}
+
+/// 出力に届かないセルを取り除く。取り除いたかどうかも返す。
+pub fn dce(nl: &Netlist) -> Result<(Netlist, bool), Error> {
+    let mut live = HashSet::new();
+    let mut stack: Vec<NetId> = nl.outputs.iter().map(|o| o.1).collect();
+    while let Some(net) = stack.pop() {
+        if let Some(c) = nl.net(net).driver {
+            if live.insert(c) {
+                stack.extend(&nl.cell(c).inputs);
+            }
+        }
+    }
+    let changed = live.len() < nl.cells.len();
+    let nl = rebuild(
+        nl,
+        |id, _| live.contains(&id),
+        |dst, kind, inputs| dst.add_cell(kind, inputs),
+    )?;
+    Ok((nl, changed))
+}
+
+fn const_of(nl: &Netlist, net: NetId) -> Option<u64> {
+    match nl.driver(net)?.kind {
+        CellKind::Const { value, width } => Some(value & mask(width)),
+        _ => None,
+    }
+}
+
+/// `nl`を作り直す。`keep`を満たすレジスタ以外のセルは依存順に`emit`へ渡され、
+/// `emit`は新しいネットリストにそのセルの値を表すネットを用意して返す。
+/// 入出力とレジスタの名前と順序は保つ。レジスタを通らないループがあるとエラー。
+fn rebuild<K, F>(nl: &Netlist, keep: K, mut emit: F) -> Result<Netlist, Error>
+where
+    K: Fn(super::CellId, &super::Cell) -> bool,
+    F: FnMut(&mut Netlist, CellKind, &[NetId]) -> NetId,
+{
+    let mut dst = Netlist::new();
+    let mut map: HashMap<NetId, NetId> = HashMap::new();
+    for &i in &nl.inputs {
+        let net = nl.net(i);
+        map.insert(i, dst.add_input(net.name.unwrap(), net.width));
+    }
+    let regs: Vec<_> = nl
+        .registers()
+        .filter(|&(id, c)| keep(id, c))
+        .map(|(_, c)| c.clone())
+        .collect();
+    for r in &regs {
+        let net = nl.net(r.output);
+        map.insert(r.output, dst.add_net(net.width, net.name));
+    }
+    let order = nl
+        .topo_order()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "combinational loop in netlist"))?;
+    for id in order {
+        let cell = nl.cell(id);
+        if !keep(id, cell) {
+            continue;
+        }
+        let inputs: Vec<NetId> = cell.inputs.iter().map(|n| map[n]).collect();
+        let out = emit(&mut dst, cell.kind, &inputs);
+        if let (Some(name), None) = (nl.net(cell.output).name, dst.net(out).name) {
+            dst.set_name(out, name);
+        }
+        map.insert(cell.output, out);
+    }
+    for r in &regs {
+        dst.connect(map[&r.output], r.kind, &[map[&r.inputs[0]]]);
+    }
+    for &(name, net) in &nl.outputs {
+        dst.add_output(name, map[&net]);
+    }
+    Ok(dst)
+}
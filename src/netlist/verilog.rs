@@ -0,0 +1,280 @@
+use super::{CellKind, NetId, Netlist};
+use std::collections::HashSet;
+
+/// IEEE 1364-2005 の予約語(Annex B)。変数名と同じならつけ直す。
+const KEYWORDS: &[&str] = &[
+    "always",
+    "and",
+    "assign",
+    "automatic",
+    "begin",
+    "buf",
+    "bufif0",
+    "bufif1",
+    "case",
+    "casex",
+    "casez",
+    "cell",
+    "cmos",
+    "config",
+    "deassign",
+    "default",
+    "defparam",
+    "design",
+    "disable",
+    "edge",
+    "else",
+    "end",
+    "endcase",
+    "endconfig",
+    "endfunction",
+    "endgenerate",
+    "endmodule",
+    "endprimitive",
+    "endspecify",
+    "endtable",
+    "endtask",
+    "event",
+    "for",
+    "force",
+    "forever",
+    "fork",
+    "function",
+    "generate",
+    "genvar",
+    "highz0",
+    "highz1",
+    "if",
+    "ifnone",
+    "incdir",
+    "include",
+    "initial",
+    "inout",
+    "input",
+    "instance",
+    "integer",
+    "join",
+    "large",
+    "liblist",
+    "library",
+    "localparam",
+    "macromodule",
+    "medium",
+    "module",
+    "nand",
+    "negedge",
+    "nmos",
+    "nor",
+    "noshowcancelled",
+    "not",
+    "notif0",
+    "notif1",
+    "or",
+    "output",
+    "parameter",
+    "pmos",
+    "posedge",
+    "primitive",
+    "pull0",
+    "pull1",
+    "pulldown",
+    "pullup",
+    "pulsestyle_ondetect",
+    "pulsestyle_onevent",
+    "rcmos",
+    "real",
+    "realtime",
+    "reg",
+    "release",
+    "repeat",
+    "rnmos",
+    "rpmos",
+    "rtran",
+    "rtranif0",
+    "rtranif1",
+    "scalared",
+    "showcancelled",
+    "signed",
+    "small",
+    "specify",
+    "specparam",
+    "strong0",
+    "strong1",
+    "supply0",
+    "supply1",
+    "table",
+    "task",
+    "time",
+    "tran",
+    "tranif0",
+    "tranif1",
+    "tri",
+    "tri0",
+    "tri1",
+    "triand",
+    "trior",
+    "trireg",
+    "unsigned",
+    "use",
+    "uwire",
+    "vectored",
+    "wait",
+    "wand",
+    "weak0",
+    "weak1",
+    "while",
+    "wire",
+    "wor",
+    "xnor",
+    "xor",
+];
+
+/// 予約語とすでに使った名前を避けて、重ならない名前を付ける
+#[derive(Default)]
+pub(super) struct Namer {
+    used: HashSet<String>,
+}
+
+impl Namer {
+    /// `base`が使えなければ`base_0`、`base_1`、…の最初に空いているもの
+    pub(super) fn unique(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut k = 0;
+        while KEYWORDS.contains(&name.as_str()) || !self.used.insert(name.clone()) {
+            name = format!("{}_{}", base, k);
+            k += 1;
+        }
+        name
+    }
+}
+
+impl Netlist {
+    /// 合成できるVerilog-2005のモジュールにする。
+    ///
+    /// 暗黙のクロックは`clk`という入力になり、レジスタはすべてその立ち上がりで更新される。
+    /// 入出力と名前のあるネットは元の変数名を保ち、同じ変数の途中の値には番号を付ける。
+    pub fn to_verilog(&self, module: &str) -> String {
+        let mut namer = Namer::default();
+        let mut unique = |base: &str| namer.unique(base);
+
+        let mut names: Vec<Option<String>> = vec![None; self.nets.len()];
+        let mut ports = Vec::new();
+        for &i in &self.inputs {
+            let name = unique(self.net(i).name.map_or("in", |s| s.as_str()));
+            ports.push(format!("input wire {}{}", range(self.net(i).width), name));
+            names[i.index()] = Some(name);
+        }
+        let mut assigns = Vec::new();
+        for &(sym, net) in &self.outputs {
+            let name = unique(sym.as_str());
+            let is_reg = self.driver(net).is_some_and(|c| c.kind.is_register());
+            if is_reg && names[net.index()].is_none() {
+                ports.push(format!("output reg {}{}", range(self.net(net).width), name));
+                names[net.index()] = Some(name);
+            } else {
+                ports.push(format!(
+                    "output wire {}{}",
+                    range(self.net(net).width),
+                    name
+                ));
+                assigns.push((name, net));
+            }
+        }
+        let clk = unique("clk");
+        ports.insert(0, format!("input wire {}", clk));
+
+        let mut decls = String::new();
+        for (id, net) in self.nets() {
+            if names[id.index()].is_some() || net.driver.is_none() {
+                continue;
+            }
+            let name = match net.name {
+                Some(s) => unique(s.as_str()),
+                None => unique(&format!("n{}", id.index())),
+            };
+            let kw = match self.driver(id) {
+                Some(c) if c.kind.is_register() => "reg",
+                _ => "wire",
+            };
+            decls += &format!("  {} {}{};\n", kw, range(net.width), name);
+            names[id.index()] = Some(name);
+        }
+        let name = |n: NetId| names[n.index()].as_deref().unwrap_or("'bx");
+
+        let mut s = format!("module {} (\n", module);
+        s += &ports
+            .iter()
+            .map(|p| format!("  {}", p))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        s += "\n);\n";
+        s += &decls;
+
+        let order = self.topo_order().unwrap_or_else(|| {
+            self.cells()
+                .filter(|(_, c)| !c.kind.is_register())
+                .map(|(id, _)| id)
+                .collect()
+        });
+        for id in order {
+            let cell = self.cell(id);
+            let a = |i: usize| name(cell.inputs[i]);
+            let w = self.net(cell.output).width;
+            let expr = match cell.kind {
+                CellKind::Const { value, width } => format!("{}'d{}", width, value),
+                CellKind::Add => format!("{} + {}", a(0), a(1)),
+                CellKind::Sub => format!("{} - {}", a(0), a(1)),
+                CellKind::Mul => format!("{} * {}", a(0), a(1)),
+                CellKind::Div => format!(
+                    "{b} == 0 ? -{w}'sd1 : $signed({}) / $signed({b})",
+                    a(0),
+                    b = a(1),
+                    w = w
+                ),
+                CellKind::Neg => format!("-{}", a(0)),
+                CellKind::Eq => format!("{} == {}", a(0), a(1)),
+                CellKind::Ne => format!("{} != {}", a(0), a(1)),
+                CellKind::Gt => format!("$signed({}) > $signed({})", a(0), a(1)),
+                CellKind::Ge => format!("$signed({}) >= $signed({})", a(0), a(1)),
+                CellKind::Concat => format!(
+                    "{{{}}}",
+                    cell.inputs
+                        .iter()
+                        .map(|&n| name(n))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                CellKind::Dff { .. } => unreachable!(),
+            };
+            s += &format!("  assign {} = {};\n", name(cell.output), expr);
+        }
+        for (port, net) in &assigns {
+            s += &format!("  assign {} = {};\n", port, name(*net));
+        }
+
+        let regs: Vec<_> = self.registers().map(|(_, c)| c).collect();
+        if !regs.is_empty() {
+            s += "  initial begin\n";
+            for c in &regs {
+                if let CellKind::Dff { init } = c.kind {
+                    let w = self.net(c.output).width;
+                    s += &format!("    {} = {}'d{};\n", name(c.output), w, init);
+                }
+            }
+            s += &format!("  end\n  always @(posedge {}) begin\n", clk);
+            for c in &regs {
+                s += &format!("    {} <= {};\n", name(c.output), name(c.inputs[0]));
+            }
+            s += "  end\n";
+        }
+        s + "endmodule\n"
+    }
+}
+
+fn range(width: u32) -> String {
+    if width == 1 {
+        String::new()
+    } else {
+        format!("[{}:0] ", width - 1)
+    }
+}
@@ -0,0 +1,259 @@
+use super::verilog::Namer;
+use super::{CellKind, NetId, Netlist};
+use crate::json::Json;
+
+/// Yosysのパラメータと同じく、32ビットの2進数の文字列にする
+fn param(n: u32) -> Json {
+    Json::Str(format!("{:032b}", n))
+}
+
+fn bits(bits: &[Json]) -> Json {
+    Json::Arr(bits.to_vec())
+}
+
+/// Yosysのセルを一つ組み立てる
+struct YosysCell {
+    name: String,
+    ty: &'static str,
+    params: Vec<(&'static str, Json)>,
+    ports: Vec<(&'static str, bool, Vec<Json>)>,
+}
+
+impl YosysCell {
+    fn to_json(&self) -> Json {
+        Json::obj([
+            ("hide_name", Json::Num(1)),
+            ("type", self.ty.into()),
+            ("parameters", Json::obj(self.params.clone())),
+            ("attributes", Json::obj::<String, _>([])),
+            (
+                "port_directions",
+                Json::obj(
+                    self.ports.iter().map(|(p, output, _)| {
+                        (*p, if *output { "output" } else { "input" }.into())
+                    }),
+                ),
+            ),
+            (
+                "connections",
+                Json::obj(self.ports.iter().map(|(p, _, b)| (*p, bits(b)))),
+            ),
+        ])
+    }
+}
+
+impl Netlist {
+    /// Yosysの`write_json`と同じ形のJSONネットリスト。
+    ///
+    /// ビットは2から順に番号を振り、定数は`"0"`と`"1"`で書く。連結はセルにせず、
+    /// 入力のビットを並べ直すだけにする。0で割ったときの値は`$mux`で-1にする。
+    pub fn to_yosys_json(&self, module: &str) -> Json {
+        let mut next = 2;
+        let mut fresh = |w: u32| {
+            let b: Vec<Json> = (next..next + w as i64).map(Json::Num).collect();
+            next += w as i64;
+            b
+        };
+        let clk = fresh(1);
+
+        // 入力から順に決まるように、入力とレジスタの出力を先に決めておく
+        let mut net_bits: Vec<Option<Vec<Json>>> = vec![None; self.nets.len()];
+        for &i in &self.inputs {
+            net_bits[i.index()] = Some(fresh(self.net(i).width));
+        }
+        for (_, c) in self.registers() {
+            net_bits[c.output.index()] = Some(fresh(self.net(c.output).width));
+        }
+        let order = self.topo_order().unwrap_or_default();
+        for &id in &order {
+            let cell = self.cell(id);
+            let w = self.net(cell.output).width;
+            let b = match cell.kind {
+                CellKind::Const { value, .. } => (0..w)
+                    .map(|k| if (value >> k) & 1 == 1 { "1" } else { "0" }.into())
+                    .collect(),
+                CellKind::Concat => cell
+                    .inputs
+                    .iter()
+                    .rev()
+                    .flat_map(|n| net_bits[n.index()].clone().unwrap_or_default())
+                    .collect(),
+                _ => fresh(w),
+            };
+            net_bits[cell.output.index()] = Some(b);
+        }
+        let get = |n: NetId| net_bits[n.index()].clone().unwrap_or_default();
+
+        let mut cells = Vec::new();
+        for (id, cell) in self.cells() {
+            let name = match cell.kind {
+                CellKind::Dff { .. } => format!("$dff${}", id.index()),
+                k => format!("${}${}", k, id.index()),
+            };
+            let mut y = get(cell.output);
+            let width = |n: NetId| self.net(n).width;
+            let (ty, signed) = match cell.kind {
+                CellKind::Const { .. } | CellKind::Concat => continue,
+                CellKind::Dff { .. } => {
+                    cells.push(YosysCell {
+                        name,
+                        ty: "$dff",
+                        params: vec![("CLK_POLARITY", param(1)), ("WIDTH", param(y.len() as u32))],
+                        ports: vec![
+                            ("CLK", false, clk.clone()),
+                            ("D", false, get(cell.inputs[0])),
+                            ("Q", true, y),
+                        ],
+                    });
+                    continue;
+                }
+                CellKind::Neg => {
+                    cells.push(YosysCell {
+                        name,
+                        ty: "$neg",
+                        params: vec![
+                            ("A_SIGNED", param(0)),
+                            ("A_WIDTH", param(width(cell.inputs[0]))),
+                            ("Y_WIDTH", param(y.len() as u32)),
+                        ],
+                        ports: vec![("A", false, get(cell.inputs[0])), ("Y", true, y)],
+                    });
+                    continue;
+                }
+                CellKind::Div => {
+                    // Y = B == 0 ? -1 : A / B
+                    let (quot, zero) = (fresh(y.len() as u32), fresh(1));
+                    let b = get(cell.inputs[1]);
+                    cells.push(YosysCell {
+                        name: format!("{}$zero", name),
+                        ty: "$logic_not",
+                        params: vec![
+                            ("A_SIGNED", param(0)),
+                            ("A_WIDTH", param(b.len() as u32)),
+                            ("Y_WIDTH", param(1)),
+                        ],
+                        ports: vec![("A", false, b), ("Y", true, zero.clone())],
+                    });
+                    cells.push(YosysCell {
+                        name: format!("{}$mux", name),
+                        ty: "$mux",
+                        params: vec![("WIDTH", param(y.len() as u32))],
+                        ports: vec![
+                            ("A", false, quot.clone()),
+                            ("B", false, vec!["1".into(); y.len()]),
+                            ("S", false, zero),
+                            ("Y", true, y),
+                        ],
+                    });
+                    // 除算そのものの結果は`$mux`の入力につなぐ
+                    y = quot;
+                    ("$div", 1)
+                }
+                CellKind::Add => ("$add", 0),
+                CellKind::Sub => ("$sub", 0),
+                CellKind::Mul => ("$mul", 0),
+                CellKind::Eq => ("$eq", 0),
+                CellKind::Ne => ("$ne", 0),
+                CellKind::Gt => ("$gt", 1),
+                CellKind::Ge => ("$ge", 1),
+            };
+            let (a, b) = (cell.inputs[0], cell.inputs[1]);
+            cells.push(YosysCell {
+                name,
+                ty,
+                params: vec![
+                    ("A_SIGNED", param(signed)),
+                    ("A_WIDTH", param(width(a))),
+                    ("B_SIGNED", param(signed)),
+                    ("B_WIDTH", param(width(b))),
+                    ("Y_WIDTH", param(y.len() as u32)),
+                ],
+                ports: vec![("A", false, get(a)), ("B", false, get(b)), ("Y", true, y)],
+            });
+        }
+
+        // ポート名は`to_verilog`と同じ規則で付け、変数`clk`とクロックが重ならないようにする
+        let mut namer = Namer::default();
+        let mut port_names: Vec<Option<String>> = vec![None; self.nets.len()];
+        let mut ports = Vec::new();
+        for &i in &self.inputs {
+            let name = namer.unique(self.net(i).name.map_or("in", |s| s.as_str()));
+            port_names[i.index()] = Some(name.clone());
+            ports.push((
+                name,
+                Json::obj([("direction", "input".into()), ("bits", bits(&get(i)))]),
+            ));
+        }
+        for &(name, n) in &self.outputs {
+            let name = namer.unique(name.as_str());
+            port_names[n.index()].get_or_insert_with(|| name.clone());
+            ports.push((
+                name,
+                Json::obj([("direction", "output".into()), ("bits", bits(&get(n)))]),
+            ));
+        }
+        let clk_name = namer.unique("clk");
+        ports.insert(
+            0,
+            (
+                clk_name.clone(),
+                Json::obj([("direction", "input".into()), ("bits", bits(&clk))]),
+            ),
+        );
+
+        let mut netnames = vec![(
+            clk_name,
+            Json::obj([
+                ("hide_name", Json::Num(0)),
+                ("bits", bits(&clk)),
+                ("attributes", Json::obj::<String, _>([])),
+            ]),
+        )];
+        for (id, net) in self.nets() {
+            let (Some(name), Some(b)) = (net.name, &net_bits[id.index()]) else {
+                continue;
+            };
+            // 入力とレジスタの出力だけが変数そのもので、ほかは途中の値
+            let driver = self.driver(id);
+            let visible = driver.is_none_or(|c| c.kind.is_register());
+            let mut attributes = Vec::new();
+            if let Some(CellKind::Dff { init }) = driver.map(|c| c.kind) {
+                attributes.push(("init", Json::Str(format!("{:0w$b}", init, w = b.len()))));
+            }
+            let key = if let Some(port) = &port_names[id.index()] {
+                port.clone()
+            } else if visible {
+                name.to_string()
+            } else {
+                format!("${}${}", name, id.index())
+            };
+            netnames.push((
+                key,
+                Json::obj([
+                    ("hide_name", Json::Num(!visible as i64)),
+                    ("bits", bits(b)),
+                    ("attributes", Json::obj(attributes)),
+                ]),
+            ));
+        }
+
+        Json::obj([
+            ("creator", "compiler".into()),
+            (
+                "modules",
+                Json::obj([(
+                    module,
+                    Json::obj([
+                        ("attributes", Json::obj::<String, _>([])),
+                        ("ports", Json::Obj(ports)),
+                        (
+                            "cells",
+                            Json::obj(cells.iter().map(|c| (c.name.clone(), c.to_json()))),
+                        ),
+                        ("netnames", Json::Obj(netnames)),
+                    ]),
+                )]),
+            ),
+        ])
+    }
+}
@@ -0,0 +1,7 @@
+mod alloc;
+mod manager;
+mod passes;
+
+pub use alloc::CountingAlloc;
+pub use manager::{Compilation, Pass, PassManager, PassStats};
+pub use passes::{Bitblast, Lint, Lower, Optimize, Parse};
@@ -0,0 +1,64 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+/// 確保したバイト数を数えるアロケータ。
+/// バイナリで`#[global_allocator]`に指定するとパスごとのメモリ使用量が取れる。
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = unsafe { System.alloc(layout) };
+        if !p.is_null() {
+            record_alloc(layout.size());
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = unsafe { System.realloc(ptr, layout, new_size) };
+        if !p.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_alloc(new_size);
+        }
+        p
+    }
+}
+
+fn record_alloc(size: usize) {
+    INSTALLED.store(true, Ordering::Relaxed);
+    TOTAL.fetch_add(size, Ordering::Relaxed);
+    let now = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+/// `CountingAlloc`が使われているか
+pub(super) fn installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+pub(super) fn current() -> usize {
+    CURRENT.load(Ordering::Relaxed)
+}
+
+pub(super) fn total() -> usize {
+    TOTAL.load(Ordering::Relaxed)
+}
+
+pub(super) fn peak() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+
+/// ピークを今の使用量に戻す
+pub(super) fn reset_peak() {
+    PEAK.store(current(), Ordering::Relaxed);
+}
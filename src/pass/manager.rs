@@ -0,0 +1,261 @@
+use super::alloc;
+use crate::aig::Aig;
+use crate::ast::Program;
+use crate::error::{Error, ErrorKind};
+use crate::json;
+use crate::lint::{self, LintConfig};
+use crate::netlist::{Netlist, PassReport};
+use crate::tokenize::TokenGen;
+use crate::vm::Code;
+use std::time::{Duration, Instant};
+
+/// パイプラインを通して受け渡される状態。
+/// 各パスは前のパスが作ったものを読み、自分の結果を書き込む。
+pub struct Compilation {
+    pub src: TokenGen,
+    pub lint: LintConfig,
+    /// `lint`パスが見つけたもの
+    pub lints: Vec<lint::Lint>,
+    pub program: Option<Program>,
+    pub netlist: Option<Netlist>,
+    pub opt_reports: Vec<PassReport>,
+    pub aig: Option<Aig>,
+    /// `dump_after`で指定したパスの後の中間表現
+    pub dumps: Vec<(&'static str, String)>,
+}
+
+impl Compilation {
+    pub fn new(src: TokenGen) -> Self {
+        Self {
+            src,
+            lint: LintConfig::new(),
+            lints: Vec::new(),
+            program: None,
+            netlist: None,
+            opt_reports: Vec::new(),
+            aig: None,
+            dumps: Vec::new(),
+        }
+    }
+
+    /// 出力の形式`format`がAIGを要るかどうか
+    pub fn needs_aig(format: &str) -> bool {
+        matches!(format, "aag" | "aig" | "blif")
+    }
+
+    pub fn expect_program(&self) -> Result<&Program, Error> {
+        self.program
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedError, "program is not parsed yet"))
+    }
+
+    pub fn expect_netlist(&self) -> Result<&Netlist, Error> {
+        self.netlist
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedError, "netlist is not built yet"))
+    }
+
+    pub fn expect_aig(&self) -> Result<&Aig, Error> {
+        self.aig
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedError, "aig is not built yet"))
+    }
+
+    /// 結果を`format`の形式で書き出す。
+    /// `dot-ast:N`はN番目(0始まり)の文だけの構文木。
+    pub fn emit(&self, format: &str) -> Result<Vec<u8>, Error> {
+        match format {
+            "aag" => Ok(self.expect_aig()?.to_aag().into_bytes()),
+            "aig" => Ok(self.expect_aig()?.to_aig()),
+            "json-tokens" => Ok(format!("{}\n", json::tokens(&self.src)?).into_bytes()),
+            "json-ast" => Ok(format!("{}\n", json::ast(self.expect_program()?)).into_bytes()),
+            "json-diagnostics" => Ok(self.diagnostics(None).into_bytes()),
+            "dot-ast" => Ok(self.expect_program()?.to_dot().into_bytes()),
+            _ if format.starts_with("dot-ast:") => {
+                let prog = self.expect_program()?;
+                let stmt = format["dot-ast:".len()..]
+                    .parse()
+                    .ok()
+                    .and_then(|i| prog.stmts().nth(i))
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidArgument,
+                            format!("no statement: {}", format),
+                        )
+                    })?;
+                Ok(stmt.to_dot().into_bytes())
+            }
+            "dot-netlist" => Ok(self.expect_netlist()?.to_dot().into_bytes()),
+            "yosys-json" => {
+                let nl = self.expect_netlist()?;
+                Ok(format!("{}\n", nl.to_yosys_json("top")).into_bytes())
+            }
+            "bytecode" => Ok(Code::compile(self.expect_program()?)?
+                .to_string()
+                .into_bytes()),
+            "verilog" => Ok(self.expect_netlist()?.to_verilog("top").into_bytes()),
+            "blif" => Ok(self.expect_aig()?.to_blif("top").into_bytes()),
+            _ => Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("unknown output format: {}", format),
+            )),
+        }
+    }
+
+    /// 診断をJSONにする。`error`はパイプラインを止めたエラー。
+    pub fn diagnostics(&self, error: Option<&Error>) -> String {
+        // 位置のないエラーで構文解析が止まったなら、止まったトークンの位置とする
+        let pos = self
+            .program
+            .is_none()
+            .then(|| (self.src.pos(), self.src.pos()));
+        format!(
+            "{}\n",
+            json::diagnostics(&self.lints, error.map(|e| (e, e.span().or(pos))), &self.src)
+        )
+    }
+
+    /// いちばん新しい中間表現をテキストにする
+    pub fn dump(&self) -> String {
+        if let Some(aig) = &self.aig {
+            aig.to_string()
+        } else if let Some(nl) = &self.netlist {
+            let mut s = String::new();
+            for r in &self.opt_reports {
+                s += &format!("// {}\n", r);
+            }
+            s + &nl.to_string()
+        } else if let Some(prog) = &self.program {
+            format!("{:?}", prog)
+        } else {
+            String::new()
+        }
+    }
+}
+
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error>;
+}
+
+/// 一つのパスにかかった時間とメモリ。
+/// メモリは`CountingAlloc`が使われているときだけ数える。
+#[derive(Debug, Clone)]
+pub struct PassStats {
+    pub pass: &'static str,
+    pub time: Duration,
+    /// パスの間に確保したバイト数の合計
+    pub allocated: Option<usize>,
+    /// パスの開始時からの使用量の増分の最大値
+    pub peak: Option<usize>,
+}
+
+impl std::fmt::Display for PassStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<10} {:>10.3}ms",
+            self.pass,
+            self.time.as_secs_f64() * 1000.0
+        )?;
+        if let (Some(a), Some(p)) = (self.allocated, self.peak) {
+            write!(f, "  alloc {:>10}B  peak {:>10}B", a, p)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    dump_after: Vec<String>,
+    stop_after: Option<String>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// parse, lint, lower, optimizeの順の標準のパイプライン。
+    /// AIGは要る出力のときだけ`Bitblast`を足して作る。
+    pub fn standard() -> Self {
+        let mut pm = Self::new();
+        pm.add(Box::new(super::Parse));
+        pm.add(Box::new(super::Lint));
+        pm.add(Box::new(super::Lower));
+        pm.add(Box::new(super::Optimize));
+        pm
+    }
+
+    pub fn add(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn pass_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().map(|p| p.name())
+    }
+
+    /// `names`にあるパスだけを残す
+    pub fn retain(&mut self, names: &[&str]) -> Result<(), Error> {
+        for &n in names {
+            self.check_name(n)?;
+        }
+        self.passes.retain(|p| names.contains(&p.name()));
+        Ok(())
+    }
+
+    /// パス`name`の後の中間表現を`Compilation::dumps`に残す。`all`ならすべてのパスの後。
+    pub fn dump_after(&mut self, name: &str) -> Result<(), Error> {
+        if name != "all" {
+            self.check_name(name)?;
+        }
+        self.dump_after.push(name.to_string());
+        Ok(())
+    }
+
+    pub fn stop_after(&mut self, name: &str) -> Result<(), Error> {
+        self.check_name(name)?;
+        self.stop_after = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn run(&mut self, cx: &mut Compilation) -> Result<Vec<PassStats>, Error> {
+        let mut stats = Vec::new();
+        for pass in &mut self.passes {
+            let name = pass.name();
+            alloc::reset_peak();
+            let (total, current) = (alloc::total(), alloc::current());
+            let start = Instant::now();
+            pass.run(cx)?;
+            let time = start.elapsed();
+            let measured = alloc::installed();
+            stats.push(PassStats {
+                pass: name,
+                time,
+                allocated: measured.then(|| alloc::total() - total),
+                peak: measured.then(|| alloc::peak() - std::cmp::min(current, alloc::peak())),
+            });
+            if self.dump_after.iter().any(|d| d == name || d == "all") {
+                let dump = cx.dump();
+                cx.dumps.push((name, dump));
+            }
+            if self.stop_after.as_deref() == Some(name) {
+                break;
+            }
+        }
+        Ok(stats)
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), Error> {
+        if self.passes.iter().any(|p| p.name() == name) {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("unknown pass `{}`", name),
+            ))
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Compilation, Pass};
+use crate::aig::Aig;
+use crate::ast::Program;
+use crate::error::Error;
+use crate::lint::{self, Linter};
+use crate::netlist::{self, Netlist};
+
+/// トークン列から構文木を作る
+pub struct Parse;
+
+impl Pass for Parse {
+    fn name(&self) -> &'static str {
+        "parse"
+    }
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error> {
+        cx.program = Some(Program::from_tokens(&mut cx.src)?);
+        Ok(())
+    }
+}
+
+/// リントを表示し、エラーレベルのものがあれば止める
+pub struct Lint;
+
+impl Pass for Lint {
+    fn name(&self) -> &'static str {
+        "lint"
+    }
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error> {
+        let prog = cx.expect_program()?;
+        let lints = Linter::with_config(&cx.lint)?.run(prog, &cx.src);
+        for l in &lints {
+            l.report(&cx.src);
+        }
+        let result = lint::check(&lints);
+        cx.lints = lints;
+        result
+    }
+}
+
+pub struct Lower;
+
+impl Pass for Lower {
+    fn name(&self) -> &'static str {
+        "lower"
+    }
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error> {
+        cx.netlist = Some(Netlist::from_program(cx.expect_program()?)?);
+        Ok(())
+    }
+}
+
+pub struct Optimize;
+
+impl Pass for Optimize {
+    fn name(&self) -> &'static str {
+        "optimize"
+    }
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error> {
+        let (nl, reports) = netlist::optimize(cx.expect_netlist()?)?;
+        cx.netlist = Some(nl);
+        cx.opt_reports = reports;
+        Ok(())
+    }
+}
+
+/// ネットリストをAIGに展開する
+pub struct Bitblast;
+
+impl Pass for Bitblast {
+    fn name(&self) -> &'static str {
+        "aig"
+    }
+
+    fn run(&mut self, cx: &mut Compilation) -> Result<(), Error> {
+        cx.aig = Some(Aig::from_netlist(cx.expect_netlist()?)?);
+        Ok(())
+    }
+}
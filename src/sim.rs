@@ -0,0 +1,72 @@
+mod cycle;
+mod event;
+mod testbench;
+
+pub use cycle::CycleSim;
+pub use event::EventSim;
+pub use testbench::{Bits, Testbench};
+
+use crate::error::{Error, ErrorKind};
+use crate::netlist::{NetId, Netlist};
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+/// シミュレータに共通の操作。`step`一回がクロック一周期で、
+/// その後の出力は`Program::compile`を一回呼んだ後の変数の値に当たる。
+pub trait Simulator {
+    /// 入力`name`の値を変える。入力でない名前なら`false`。
+    fn set(&mut self, name: Symbol, value: u64) -> bool;
+
+    /// 名前の付いた入力か出力の値
+    fn get(&self, name: Symbol) -> Option<u64>;
+
+    fn value(&self, net: NetId) -> u64;
+
+    /// クロックを進めずに、入力の変化を組み合わせ回路に行き渡らせる
+    fn settle(&mut self) -> Result<(), Error>;
+
+    fn step(&mut self) -> Result<(), Error>;
+
+    /// これまでに進めたサイクルの数
+    fn cycle(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Event,
+    Cycle,
+}
+
+impl Engine {
+    pub fn parse(name: &str) -> Result<Engine, Error> {
+        match name {
+            "event" => Ok(Engine::Event),
+            "cycle" => Ok(Engine::Cycle),
+            _ => Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("unknown simulator: {}", name),
+            )),
+        }
+    }
+
+    pub fn build<'a>(self, nl: &'a Netlist) -> Result<Box<dyn Simulator + 'a>, Error> {
+        Ok(match self {
+            Engine::Event => Box::new(EventSim::new(nl, 10)),
+            Engine::Cycle => Box::new(CycleSim::new(nl)?),
+        })
+    }
+}
+
+/// 入力と出力の名前からネットを引く表
+fn port_names(nl: &Netlist) -> HashMap<Symbol, NetId> {
+    let mut names = HashMap::new();
+    for &i in nl.inputs() {
+        if let Some(name) = nl.net(i).name {
+            names.insert(name, i);
+        }
+    }
+    for &(name, n) in nl.outputs() {
+        names.insert(name, n);
+    }
+    names
+}
@@ -0,0 +1,137 @@
+use super::{port_names, Simulator};
+use crate::error::{Error, ErrorKind};
+use crate::netlist::{mask, CellKind, NetId, Netlist};
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+/// 前もって並べた組み合わせセル一つ分の評価
+struct Inst {
+    kind: CellKind,
+    inputs: Vec<(usize, u32)>,
+    output: usize,
+}
+
+/// 単一クロックの同期回路のためのサイクルベースのシミュレータ。
+///
+/// 組み合わせセルは作るときに一度だけトポロジカル順に並べ、各サイクルでは
+/// その順に評価してからレジスタを一斉に更新する。イベントのキューは使わない。
+pub struct CycleSim<'a> {
+    nl: &'a Netlist,
+    insts: Vec<Inst>,
+    /// レジスタの(D, Q)
+    regs: Vec<(usize, usize)>,
+    values: Vec<u64>,
+    names: HashMap<Symbol, NetId>,
+    args: Vec<(u64, u32)>,
+    /// 入力が変わって組み合わせ回路の値が古くなっているか
+    dirty: bool,
+    cycle: u64,
+}
+
+impl<'a> CycleSim<'a> {
+    /// レジスタを通らないループがあるとエラー
+    pub fn new(nl: &'a Netlist) -> Result<Self, Error> {
+        let order = nl.topo_order().ok_or_else(|| {
+            Error::new(
+                ErrorKind::SimulationError,
+                "combinational loop; use the event-driven simulator",
+            )
+        })?;
+        let insts = order
+            .into_iter()
+            .map(|id| {
+                let cell = nl.cell(id);
+                Inst {
+                    kind: cell.kind,
+                    inputs: cell
+                        .inputs
+                        .iter()
+                        .map(|&n| (n.index(), nl.net(n).width))
+                        .collect(),
+                    output: cell.output.index(),
+                }
+            })
+            .collect();
+        let mut values = vec![0; nl.nets().len()];
+        let mut regs = Vec::new();
+        for (_, cell) in nl.registers() {
+            if let CellKind::Dff { init } = cell.kind {
+                values[cell.output.index()] = init;
+            }
+            regs.push((cell.inputs[0].index(), cell.output.index()));
+        }
+        Ok(Self {
+            nl,
+            insts,
+            regs,
+            values,
+            names: port_names(nl),
+            args: Vec::new(),
+            dirty: true,
+            cycle: 0,
+        })
+    }
+
+    fn eval(&mut self) {
+        for inst in &self.insts {
+            self.args.clear();
+            self.args
+                .extend(inst.inputs.iter().map(|&(n, w)| (self.values[n], w)));
+            self.values[inst.output] = inst.kind.eval(&self.args);
+        }
+        self.dirty = false;
+    }
+}
+
+impl Simulator for CycleSim<'_> {
+    /// 幅を超える上位ビットは捨てる
+    fn set(&mut self, name: Symbol, value: u64) -> bool {
+        match self.names.get(&name) {
+            Some(&n) if self.nl.inputs().contains(&n) => {
+                let value = value & mask(self.nl.net(n).width);
+                if self.values[n.index()] != value {
+                    self.values[n.index()] = value;
+                    self.dirty = true;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn get(&self, name: Symbol) -> Option<u64> {
+        self.names.get(&name).map(|&n| self.value(n))
+    }
+
+    fn value(&self, net: NetId) -> u64 {
+        self.values[net.index()]
+    }
+
+    fn settle(&mut self) -> Result<(), Error> {
+        if self.dirty {
+            self.eval();
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        if self.dirty {
+            self.eval();
+        }
+        // Dをすべて読んでからQに書く
+        self.args.clear();
+        self.args
+            .extend(self.regs.iter().map(|&(d, _)| (self.values[d], 0)));
+        for (&(_, q), &(v, _)) in self.regs.iter().zip(&self.args) {
+            self.values[q] = v;
+        }
+        // 新しいQから組み合わせ回路の値を更新しておく
+        self.eval();
+        self.cycle += 1;
+        Ok(())
+    }
+
+    fn cycle(&self) -> u64 {
+        self.cycle
+    }
+}
@@ -0,0 +1,225 @@
+use super::{port_names, Simulator};
+use crate::error::{Error, ErrorKind};
+use crate::netlist::{mask, CellId, CellKind, NetId, Netlist};
+use crate::symbol::Symbol;
+use std::collections::{BTreeMap, HashMap};
+
+/// 一つの時刻で許すデルタサイクルの数。これを超えたら組み合わせ回路が発振しているとみなす。
+const DELTA_LIMIT: u64 = 10_000;
+
+/// 未来の時刻に予約するイベント。今はクロックの端だけ。
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    Clock(bool),
+}
+
+/// ネットリストのイベント駆動シミュレータ。
+///
+/// 一つの時刻はVerilogと同じくアクティブ、インアクティブ、NBAの三つの領域で進む。
+/// レジスタ以外のセルはそれぞれ入力のネットに感応するプロセスで、入力が変わると
+/// 次のデルタサイクルでアクティブ領域で評価される。`set`で今の時刻に入れた入力は
+/// `#0`の代入と同じくインアクティブ領域に入り、アクティブ領域が空になってから反映する。
+/// レジスタはクロックの立ち上がりに感応するプロセスで、Dを読んでQの更新をNBA領域に
+/// 積む。NBA領域はアクティブとインアクティブの両方が空になってから反映する。
+///
+/// クロックは周期`period`で、`step`一回がちょうど一周期になる。
+pub struct EventSim<'a> {
+    nl: &'a Netlist,
+    values: Vec<u64>,
+    /// ネットを読む組み合わせセル
+    fanout: Vec<Vec<CellId>>,
+    names: HashMap<Symbol, NetId>,
+    queue: BTreeMap<u64, Vec<Event>>,
+    /// 次のデルタで評価するセルと、その重複を防ぐ印
+    active: Vec<CellId>,
+    triggered: Vec<bool>,
+    /// 今の時刻のインアクティブ領域とNBA領域に積んだ値の更新
+    inactive: Vec<(NetId, u64)>,
+    nba: Vec<(NetId, u64)>,
+    time: u64,
+    period: u64,
+    deltas: u64,
+    events: u64,
+    cycle: u64,
+}
+
+impl<'a> EventSim<'a> {
+    pub fn new(nl: &'a Netlist, period: u64) -> Self {
+        let mut fanout = vec![Vec::new(); nl.nets().len()];
+        let mut values = vec![0; nl.nets().len()];
+        let mut active = Vec::new();
+        for (id, cell) in nl.cells() {
+            if let CellKind::Dff { init } = cell.kind {
+                values[cell.output.index()] = init;
+                continue;
+            }
+            for &n in &cell.inputs {
+                fanout[n.index()].push(id);
+            }
+            // 時刻0ですべての組み合わせセルを一度評価する
+            active.push(id);
+        }
+        Self {
+            nl,
+            values,
+            fanout,
+            names: port_names(nl),
+            queue: BTreeMap::new(),
+            triggered: vec![true; nl.cells().len()],
+            active,
+            inactive: Vec::new(),
+            nba: Vec::new(),
+            time: 0,
+            period: period.max(2),
+            deltas: 0,
+            events: 0,
+            cycle: 0,
+        }
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// これまでに回したデルタサイクルの数
+    pub fn deltas(&self) -> u64 {
+        self.deltas
+    }
+
+    /// これまでに起きた値の変化の数
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+
+    fn schedule(&mut self, time: u64, event: Event) {
+        self.queue.entry(time).or_default().push(event);
+    }
+
+    /// 時刻`until`までのイベントをすべて処理する
+    pub fn run_until(&mut self, until: u64) -> Result<(), Error> {
+        // 時刻0の初期化の評価が残っていれば先に済ませる
+        self.settle()?;
+        loop {
+            let time = match self.queue.keys().next() {
+                Some(&t) if t <= until => t,
+                _ => break,
+            };
+            self.time = time;
+            for event in self.queue.remove(&time).unwrap_or_default() {
+                match event {
+                    Event::Clock(true) => self.posedge(),
+                    Event::Clock(false) => {}
+                }
+            }
+            self.settle()?;
+        }
+        self.time = self.time.max(until);
+        Ok(())
+    }
+
+    /// レジスタのプロセス。Dを読み、Qの更新をNBA領域に積む。
+    fn posedge(&mut self) {
+        for (_, cell) in self.nl.registers() {
+            let d = self.values[cell.inputs[0].index()];
+            self.nba.push((cell.output, d));
+        }
+    }
+
+    /// ネットの値を変え、変わったならそれを読むセルを起こす
+    fn update(&mut self, net: NetId, value: u64) {
+        if self.values[net.index()] == value {
+            return;
+        }
+        self.values[net.index()] = value;
+        self.events += 1;
+        for &c in &self.fanout[net.index()] {
+            if !std::mem::replace(&mut self.triggered[c.index()], true) {
+                self.active.push(c);
+            }
+        }
+    }
+
+    /// アクティブ、インアクティブ、NBAの領域がすべて空になるまでデルタサイクルを回す
+    fn settle(&mut self) -> Result<(), Error> {
+        let mut count = 0;
+        loop {
+            if self.active.is_empty() {
+                let region = if !self.inactive.is_empty() {
+                    &mut self.inactive
+                } else if !self.nba.is_empty() {
+                    &mut self.nba
+                } else {
+                    return Ok(());
+                };
+                for (net, value) in std::mem::take(region) {
+                    self.update(net, value);
+                }
+                continue;
+            }
+            count += 1;
+            self.deltas += 1;
+            if count > DELTA_LIMIT {
+                return Err(Error::new(
+                    ErrorKind::SimulationError,
+                    format!("combinational loop does not settle at time {}", self.time),
+                ));
+            }
+            // このデルタの評価をすべて済ませてから値を反映する
+            let cells = std::mem::take(&mut self.active);
+            let mut updates = Vec::with_capacity(cells.len());
+            for c in cells {
+                self.triggered[c.index()] = false;
+                let cell = self.nl.cell(c);
+                let args: Vec<(u64, u32)> = cell
+                    .inputs
+                    .iter()
+                    .map(|&n| (self.values[n.index()], self.nl.net(n).width))
+                    .collect();
+                updates.push((cell.output, cell.kind.eval(&args)));
+            }
+            for (net, value) in updates {
+                self.update(net, value);
+            }
+        }
+    }
+}
+
+impl Simulator for EventSim<'_> {
+    /// 入力は現在の時刻のインアクティブ領域で変わる。幅を超える上位ビットは捨てる。
+    fn set(&mut self, name: Symbol, value: u64) -> bool {
+        match self.names.get(&name) {
+            Some(&n) if self.nl.inputs().contains(&n) => {
+                let value = value & mask(self.nl.net(n).width);
+                self.inactive.push((n, value));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn value(&self, net: NetId) -> u64 {
+        self.values[net.index()]
+    }
+
+    fn get(&self, name: Symbol) -> Option<u64> {
+        self.names.get(&name).map(|&n| self.value(n))
+    }
+
+    fn settle(&mut self) -> Result<(), Error> {
+        self.run_until(self.time)
+    }
+
+    /// クロックを一周期進める。今の時刻に入力を反映し、半周期後の立ち上がりで
+    /// レジスタを更新し、一周期後の立ち下がりまで進む。
+    fn step(&mut self) -> Result<(), Error> {
+        let start = self.time;
+        self.schedule(start + self.period / 2, Event::Clock(true));
+        self.schedule(start + self.period, Event::Clock(false));
+        self.cycle += 1;
+        self.run_until(start + self.period)
+    }
+
+    fn cycle(&self) -> u64 {
+        self.cycle
+    }
+}
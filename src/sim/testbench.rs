@@ -0,0 +1,142 @@
+use super::{Engine, Simulator};
+use crate::error::{Error, ErrorKind};
+use crate::netlist::{mask, sext, NetId, Netlist};
+use crate::symbol::Symbol;
+
+/// 幅の決まった値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bits {
+    value: u64,
+    width: u32,
+}
+
+impl Bits {
+    /// 幅を超える上位ビットは捨てる
+    pub fn new(value: u64, width: u32) -> Self {
+        Self {
+            value: value & mask(width),
+            width,
+        }
+    }
+
+    pub fn width(self) -> u32 {
+        self.width
+    }
+
+    /// `i`ビット目(0が最下位)
+    pub fn bit(self, i: u32) -> bool {
+        i < self.width && (self.value >> i) & 1 == 1
+    }
+
+    pub fn to_u64(self) -> u64 {
+        self.value
+    }
+
+    /// 符号付きとして読んだ値
+    pub fn to_i64(self) -> i64 {
+        sext(self.value, self.width)
+    }
+}
+
+impl PartialEq<u64> for Bits {
+    fn eq(&self, other: &u64) -> bool {
+        self.value == *other
+    }
+}
+
+impl PartialEq<i64> for Bits {
+    fn eq(&self, other: &i64) -> bool {
+        self.to_i64() == *other
+    }
+}
+
+/// 上位ビットから幅いっぱいに並べた2進数
+impl std::fmt::Display for Bits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:0w$b}", self.value, w = self.width as usize)
+    }
+}
+
+/// Rustのテストから回路を動かすための薄い層。
+///
+/// 入出力は名前で指定する。`poke`した入力は次の`step`の立ち上がりで取り込まれ、
+/// `peek`は今の値を返す。
+pub struct Testbench<'a> {
+    nl: &'a Netlist,
+    sim: Box<dyn Simulator + 'a>,
+}
+
+impl<'a> Testbench<'a> {
+    pub fn new(nl: &'a Netlist, engine: Engine) -> Result<Self, Error> {
+        let mut sim = engine.build(nl)?;
+        sim.settle()?;
+        Ok(Self { nl, sim })
+    }
+
+    /// 名前の付いた入力か出力のネット
+    fn port(&self, name: &str) -> Result<NetId, Error> {
+        let sym = Symbol::intern(name);
+        self.nl
+            .inputs()
+            .iter()
+            .copied()
+            .find(|&n| self.nl.net(n).name == Some(sym))
+            .or_else(|| self.nl.outputs().iter().find(|o| o.0 == sym).map(|o| o.1))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!("no port named {}", name),
+                )
+            })
+    }
+
+    /// 入力`name`に値を入れる。幅を超える上位ビットは捨てる。
+    pub fn poke(&mut self, name: &str, value: i64) -> Result<(), Error> {
+        let net = self.port(name)?;
+        let value = value as u64 & mask(self.nl.net(net).width);
+        if !self.sim.set(Symbol::intern(name), value) {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("{} is not an input", name),
+            ));
+        }
+        self.sim.settle()
+    }
+
+    pub fn peek(&self, name: &str) -> Result<Bits, Error> {
+        let net = self.port(name)?;
+        Ok(Bits::new(self.sim.value(net), self.nl.net(net).width))
+    }
+
+    /// クロックを`n`サイクル進める
+    pub fn step(&mut self, n: u64) -> Result<(), Error> {
+        for _ in 0..n {
+            self.sim.step()?;
+        }
+        Ok(())
+    }
+
+    /// 出力`name`が符号付きで`expected`であることを確かめる。違えばサイクル数と一緒にpanicする。
+    #[track_caller]
+    pub fn expect(&self, name: &str, expected: i64) {
+        let got = self
+            .peek(name)
+            .unwrap_or_else(|e| panic!("{}: {}", e, e.detail().unwrap_or_default()));
+        assert!(
+            got == expected,
+            "cycle {}: {} = {}, expected {}",
+            self.cycle(),
+            name,
+            got.to_i64(),
+            expected
+        );
+    }
+
+    pub fn cycle(&self) -> u64 {
+        self.sim.cycle()
+    }
+
+    pub fn simulator(&self) -> &dyn Simulator {
+        self.sim.as_ref()
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// 文字列を登録した番号。
+/// 同じ文字列は常に同じ`Symbol`になるので、比較とハッシュは番号だけで済む。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    map: HashMap<&'static str, Symbol>,
+    strs: Vec<&'static str>,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(s: &str) -> Self {
+        let mut i = interner().lock().unwrap();
+        if let Some(&sym) = i.map.get(s) {
+            return sym;
+        }
+        // 登録した文字列はプログラムの終了まで解放しない
+        let s: &'static str = Box::leak(s.to_string().into_boxed_str());
+        let sym = Symbol(i.strs.len() as u32);
+        i.strs.push(s);
+        i.map.insert(s, sym);
+        sym
+    }
+
+    pub fn from_chars(s: &[char]) -> Self {
+        Self::intern(&s.iter().collect::<String>())
+    }
+
+    pub fn as_str(self) -> &'static str {
+        interner().lock().unwrap().strs[self.0 as usize]
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
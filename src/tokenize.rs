@@ -2,6 +2,6 @@ mod generator;
 mod kind;
 mod token;
 
-pub use generator::TokenGen;
+pub use generator::{Span, TokenGen};
 pub use kind::TokenKind;
 pub use token::Token;
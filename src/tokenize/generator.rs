@@ -1,13 +1,19 @@
 use super::{kind::TokenKind, token::Token};
 use crate::datatype::DataUnion;
 use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
 use std::io::Read;
 
+/// ソース上の文字単位の半開区間
+pub type Span = (usize, usize);
+
 pub struct TokenGen {
     code: Vec<char>,
     index: usize,
     prev: usize,
+    last_end: usize,
     current: Token,
+    comments: Vec<(usize, usize)>,
 }
 
 impl TokenGen {
@@ -22,11 +28,17 @@ impl TokenGen {
         if let Err(e) = f.read_to_string(&mut s) {
             return Err(Error::new(ErrorKind::CannotReadFile, e));
         };
+        Self::from_code(&s)
+    }
+
+    pub fn from_code(code: &str) -> Result<Self, Error> {
         let mut se = Self {
-            code: s.chars().collect(),
+            code: code.chars().collect(),
             index: 0,
             prev: 0,
+            last_end: 0,
             current: Token::new(TokenKind::Eof, DataUnion::None),
+            comments: Vec::new(),
         };
         se.next()?;
         Ok(se)
@@ -35,6 +47,7 @@ impl TokenGen {
     fn next(&mut self) -> Result<(), Error> {
         let l = self.code.len();
         let mut i = self.index;
+        self.last_end = self.index;
         loop {
             if i >= l {
                 self.current = Token::new(TokenKind::Eof, DataUnion::None);
@@ -42,6 +55,12 @@ impl TokenGen {
             }
             if is_space(self.code[i]) {
                 i += 1;
+            } else if self.code[i] == '/' && i + 1 < l && self.code[i + 1] == '/' {
+                let start = i;
+                while i < l && self.code[i] != '\n' {
+                    i += 1;
+                }
+                self.comments.push((start, i));
             } else {
                 break;
             }
@@ -58,10 +77,7 @@ impl TokenGen {
             while i < l && (is_ident(self.code[i]) || self.code[i].is_ascii_digit()) {
                 i += 1;
             }
-            self.current = Token::new(
-                TokenKind::Ident,
-                DataUnion::String(self.code[self.index..i].to_vec()),
-            );
+            self.current = Token::new(TokenKind::Ident, DataUnion::cstr(&self.code[self.index..i]));
             self.index = i;
             Ok(())
         } else if is_mark(self.code[i]) {
@@ -71,7 +87,7 @@ impl TokenGen {
             }
             self.current = Token::new(
                 TokenKind::Reserved,
-                DataUnion::String(self.code[self.index..i].to_vec()),
+                DataUnion::cstr(&self.code[self.index..i]),
             );
             self.index = i;
             Ok(())
@@ -87,7 +103,7 @@ impl TokenGen {
             Ok(())
         } else {
             self.error_at("使用不可な文字が含まれています。", None);
-            Err(Error::new(ErrorKind::InvalidChar, "使用不可な文字"))
+            Err(Error::new(ErrorKind::InvalidChar, "使用不可な文字").with_span((i, i + 1)))
         }
     }
 
@@ -100,7 +116,7 @@ impl TokenGen {
             self.next()
         } else {
             self.error_at("syntax error.", None);
-            Err(Error::new(ErrorKind::SyntaxError, "構文エラー"))
+            Err(Error::new(ErrorKind::SyntaxError, "構文エラー").with_span(self.span()))
         }
     }
 
@@ -118,7 +134,7 @@ impl TokenGen {
             self.next()
         } else {
             self.error_at("syntax error.", None);
-            Err(Error::new(ErrorKind::SyntaxError, "構文エラー"))
+            Err(Error::new(ErrorKind::SyntaxError, "構文エラー").with_span(self.span()))
         }
     }
 
@@ -131,7 +147,7 @@ impl TokenGen {
                 format!("expect Number but found {:?}", self.current.get_data()),
                 None,
             );
-            Err(Error::new(ErrorKind::SyntaxError, "構文エラー"))
+            Err(Error::new(ErrorKind::SyntaxError, "構文エラー").with_span(self.span()))
         }
     }
 
@@ -139,9 +155,62 @@ impl TokenGen {
         &self.current
     }
 
-    pub fn get_ident(&mut self) -> Result<Vec<char>, Error> {
+    /// 現在のトークンの位置(文字単位の半開区間)
+    pub fn span(&self) -> (usize, usize) {
+        (self.prev, self.index)
+    }
+
+    /// 同じソースを最初から読み直す`TokenGen`
+    pub fn rewind(&self) -> Result<TokenGen, Error> {
+        TokenGen::from_code(&self.code.iter().collect::<String>())
+    }
+
+    /// 残りのトークンを終わりまで読み、位置と一緒に並べる(`Eof`は含まない)
+    pub fn read_all(&mut self) -> Result<Vec<(Token, Span)>, Error> {
+        let mut out = Vec::new();
+        while !self.current.is_kind(TokenKind::Eof) {
+            out.push((self.current.clone(), self.span()));
+            self.next()?;
+        }
+        Ok(out)
+    }
+
+    /// 現在のトークンの開始位置
+    pub fn pos(&self) -> usize {
+        self.prev
+    }
+
+    /// 直前に読み進めたトークンの終了位置
+    pub fn last_end(&self) -> usize {
+        self.last_end
+    }
+
+    /// これまでに読み飛ばした`//`コメントの位置
+    pub fn comments(&self) -> &[(usize, usize)] {
+        &self.comments
+    }
+
+    pub fn text(&self, span: (usize, usize)) -> String {
+        self.code[span.0..span.1].iter().collect()
+    }
+
+    /// 位置`pos`を含む行の番号(1始まり)
+    pub fn line_of(&self, pos: usize) -> usize {
+        self.code[..pos].iter().filter(|&&c| c == '\n').count() + 1
+    }
+
+    /// 位置`pos`を含む行で、`pos`より前が空白だけかどうか
+    pub fn starts_line(&self, pos: usize) -> bool {
+        self.code[..pos]
+            .iter()
+            .rev()
+            .take_while(|&&c| c != '\n')
+            .all(|c| is_space(*c))
+    }
+
+    pub fn get_ident(&mut self) -> Result<Symbol, Error> {
         if self.current.is_kind(TokenKind::Ident) {
-            if let DataUnion::String(s) = self.current.get_data().clone() {
+            if let DataUnion::Symbol(s) = *self.current.get_data() {
                 self.next()?;
                 Ok(s)
             } else {
@@ -152,7 +221,7 @@ impl TokenGen {
                 format!("expect Identity but found {:?}", self.current.get_data()),
                 None,
             );
-            Err(Error::new(ErrorKind::SyntaxError, "構文エラー"))
+            Err(Error::new(ErrorKind::SyntaxError, "構文エラー").with_span(self.span()))
         }
     }
 
@@ -164,7 +233,7 @@ impl TokenGen {
             Some((l, r)) => {
                 let pos1 = self.prev;
                 let pos2 = self.index;
-                println!(
+                eprintln!(
                     "{} >>>{}<<< {}",
                     self.code[(std::cmp::max(pos1, l) - l)..pos1]
                         .iter()
@@ -174,13 +243,31 @@ impl TokenGen {
                         .iter()
                         .collect::<String>()
                 );
-                println!("{}", msg);
+                eprintln!("{}", msg);
             }
             None => {
                 self.error_at(msg, Some((30, 30)));
             }
         };
     }
+
+    pub fn error_span<S>(&self, msg: S, span: (usize, usize))
+    where
+        S: std::fmt::Display,
+    {
+        let (pos1, pos2) = span;
+        eprintln!(
+            "{} >>>{}<<< {}",
+            self.code[(std::cmp::max(pos1, 30) - 30)..pos1]
+                .iter()
+                .collect::<String>(),
+            self.code[pos1..pos2].iter().collect::<String>(),
+            self.code[pos2..std::cmp::min(pos2 + 30, self.code.len())]
+                .iter()
+                .collect::<String>()
+        );
+        eprintln!("{}", msg);
+    }
 }
 
 fn is_space(ch: char) -> bool {
@@ -23,10 +23,14 @@ impl Token {
     pub fn with_str(tk: TokenKind, word: &str) -> Self {
         Token {
             tk,
-            data: DataUnion::String(word.chars().collect()),
+            data: DataUnion::str(word),
         }
     }
 
+    pub fn kind(&self) -> TokenKind {
+        self.tk
+    }
+
     pub fn is_kind(&self, kind: TokenKind) -> bool {
         self.tk == kind
     }
@@ -0,0 +1,9 @@
+mod diff;
+mod reader;
+mod recorder;
+mod writer;
+
+pub use diff::{diff, Mismatch};
+pub use reader::{Signal, Waveform};
+pub use recorder::{Filter, Recorder};
+pub use writer::{VarId, VcdWriter};
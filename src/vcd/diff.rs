@@ -0,0 +1,135 @@
+use super::reader::{extend, Signal, Waveform};
+use std::collections::HashMap;
+
+/// 二つの波形の食い違い
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// 時間の単位が違い、時刻をそのまま比べられない
+    Timescale { left: String, right: String },
+    /// 片方にしかない信号。`left`が真なら左にだけある。
+    Missing { path: String, left: bool },
+    /// `map`で対応させた右の信号が見つからない
+    Unmapped { path: String, target: String },
+    /// 最初に値が食い違った時刻と、その時の左右の値(値がなければ`None`)
+    Value {
+        path: String,
+        time: u64,
+        left: Option<String>,
+        right: Option<String>,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Timescale { left, right } => {
+                write!(f, "timescales differ: {} != {}", left, right)
+            }
+            Mismatch::Unmapped { path, target } => {
+                write!(
+                    f,
+                    "{}: mapped to {}, which is not in the second dump",
+                    path, target
+                )
+            }
+            Mismatch::Missing { path, left } => write!(
+                f,
+                "{}: only in the {} dump",
+                path,
+                if *left { "first" } else { "second" }
+            ),
+            Mismatch::Value {
+                path,
+                time,
+                left,
+                right,
+            } => write!(
+                f,
+                "{}: first mismatch at #{}: {} != {}",
+                path,
+                time,
+                left.as_deref().unwrap_or("(none)"),
+                right.as_deref().unwrap_or("(none)")
+            ),
+        }
+    }
+}
+
+/// 右の波形から信号を探す。完全な名前か、ほかに同じ名前がなければ最後の名前で引く。
+fn find<'a>(wave: &'a Waveform, name: &str) -> Option<&'a Signal> {
+    wave.signal(name).or_else(|| {
+        let mut it = wave.signals.iter().filter(|s| s.name() == name);
+        match (it.next(), it.next()) {
+            (Some(s), None) => Some(s),
+            _ => None,
+        }
+    })
+}
+
+/// 左右の信号を並べて比べ、最初に食い違った時刻を返す
+fn first_mismatch(a: &Signal, b: &Signal) -> Option<(u64, Option<String>, Option<String>)> {
+    let width = a.width.max(b.width);
+    let value = |s: &Signal, t: u64| s.value_at(t).map(|v| extend(v, width));
+    let mut times: Vec<u64> = a.changes.iter().chain(&b.changes).map(|c| c.0).collect();
+    times.sort_unstable();
+    times.dedup();
+    times
+        .into_iter()
+        .map(|t| (t, value(a, t), value(b, t)))
+        .find(|(_, l, r)| l != r)
+}
+
+/// 左の各信号を右の同じ名前(`map`があればその名前)の信号と比べる。
+/// `map`のキーと値は完全な名前でも最後の名前でもよい。
+/// 時間の単位が違えば、値は比べずにそれだけを返す。
+pub fn diff(a: &Waveform, b: &Waveform, map: &HashMap<String, String>) -> Vec<Mismatch> {
+    if a.timescale != b.timescale {
+        return vec![Mismatch::Timescale {
+            left: a.timescale.clone(),
+            right: b.timescale.clone(),
+        }];
+    }
+    let mut out = Vec::new();
+    let mut used = vec![false; b.signals.len()];
+    for s in &a.signals {
+        let target = map.get(&s.path).or_else(|| map.get(s.name()));
+        let found = match target {
+            Some(t) => find(b, t),
+            None => find(b, &s.path).or_else(|| find(b, s.name())),
+        };
+        let Some(other) = found else {
+            if let Some(t) = target {
+                out.push(Mismatch::Unmapped {
+                    path: s.path.clone(),
+                    target: t.clone(),
+                });
+                continue;
+            }
+            out.push(Mismatch::Missing {
+                path: s.path.clone(),
+                left: true,
+            });
+            continue;
+        };
+        if let Some(i) = b.signals.iter().position(|x| std::ptr::eq(x, other)) {
+            used[i] = true;
+        }
+        if let Some((time, left, right)) = first_mismatch(s, other) {
+            out.push(Mismatch::Value {
+                path: s.path.clone(),
+                time,
+                left,
+                right,
+            });
+        }
+    }
+    for (s, used) in b.signals.iter().zip(used) {
+        if !used {
+            out.push(Mismatch::Missing {
+                path: s.path.clone(),
+                left: false,
+            });
+        }
+    }
+    out
+}
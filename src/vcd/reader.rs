@@ -0,0 +1,154 @@
+use crate::error::{Error, ErrorKind};
+use std::collections::HashMap;
+
+/// VCDの一つの変数と、その値の変化
+#[derive(Debug, Clone)]
+pub struct Signal {
+    /// スコープを`.`でつないだ完全な名前(`top.comb.c_5`)
+    pub path: String,
+    pub width: u32,
+    /// 時刻と値。値は幅いっぱいの`0`、`1`、`x`、`z`の列(実数は書かれたまま)。
+    pub changes: Vec<(u64, String)>,
+}
+
+impl Signal {
+    /// 最後の`.`より後ろの名前
+    pub fn name(&self) -> &str {
+        self.path.rsplit('.').next().unwrap_or(&self.path)
+    }
+
+    /// 時刻`t`の値。まだ一度も値がなければ`None`。
+    pub fn value_at(&self, t: u64) -> Option<&str> {
+        let i = self.changes.partition_point(|c| c.0 <= t);
+        i.checked_sub(1).map(|i| self.changes[i].1.as_str())
+    }
+}
+
+/// 読み込んだVCD
+#[derive(Debug, Clone, Default)]
+pub struct Waveform {
+    pub timescale: String,
+    pub signals: Vec<Signal>,
+}
+
+impl Waveform {
+    pub fn parse(text: &str) -> Result<Waveform, Error> {
+        let mut wave = Waveform::default();
+        let mut scopes: Vec<&str> = Vec::new();
+        // 識別コードから変数へ。同じコードを複数の変数が共有できる。
+        let mut codes: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut time = 0;
+        let mut tokens = text.split_whitespace();
+        while let Some(tok) = tokens.next() {
+            match tok {
+                "$scope" => match section(&mut tokens)?.get(1) {
+                    Some(name) => scopes.push(name),
+                    None => return Err(syntax("$scope without a name")),
+                },
+                "$upscope" => {
+                    section(&mut tokens)?;
+                    scopes.pop();
+                }
+                "$var" => {
+                    let body = section(&mut tokens)?;
+                    let [_, width, code, name, ..] = body[..] else {
+                        return Err(syntax("malformed $var"));
+                    };
+                    let width = width.parse().map_err(|_| syntax("bad $var width"))?;
+                    let mut path: Vec<&str> = scopes.clone();
+                    path.push(name);
+                    codes.entry(code).or_default().push(wave.signals.len());
+                    wave.signals.push(Signal {
+                        path: path.join("."),
+                        width,
+                        changes: Vec::new(),
+                    });
+                }
+                "$timescale" => wave.timescale = section(&mut tokens)?.join(""),
+                // 値の区間の印は値の並びに影響しない
+                "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => {}
+                _ if tok.starts_with('$') => {
+                    section(&mut tokens)?;
+                }
+                _ if tok.starts_with('#') => {
+                    time = tok[1..].parse().map_err(|_| syntax("bad time"))?;
+                }
+                _ => {
+                    // 空白で区切った語なので空ではない
+                    let first = tok.chars().next().unwrap();
+                    let real = matches!(first, 'r' | 'R');
+                    let (value, code) = match first {
+                        'b' | 'B' | 'r' | 'R' => {
+                            let code = tokens
+                                .next()
+                                .ok_or_else(|| syntax("value without a code"))?;
+                            (&tok[1..], code)
+                        }
+                        '0' | '1' | 'x' | 'X' | 'z' | 'Z' => tok.split_at(first.len_utf8()),
+                        _ => return Err(syntax(&format!("unexpected {}", tok))),
+                    };
+                    let four_state = value
+                        .chars()
+                        .all(|c| matches!(c, '0' | '1' | 'x' | 'X' | 'z' | 'Z'));
+                    if value.is_empty() || code.is_empty() || (!real && !four_state) {
+                        return Err(syntax(&format!("malformed value change {}", tok)));
+                    }
+                    let vars = codes
+                        .get(code)
+                        .ok_or_else(|| syntax(&format!("unknown identifier code {}", code)))?;
+                    for &v in vars {
+                        let s = &mut wave.signals[v];
+                        let value = if real {
+                            value.to_string()
+                        } else {
+                            extend(value, s.width)
+                        };
+                        // 同じ時刻に何度も書かれたら最後の値にする
+                        match s.changes.last_mut() {
+                            Some(last) if last.0 == time => last.1 = value,
+                            _ => s.changes.push((time, value)),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(wave)
+    }
+
+    pub fn signal(&self, path: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.path == path)
+    }
+}
+
+/// VCDの規則で幅`width`まで上位を埋める。`x`と`z`はその値で、ほかは0で埋める。
+pub(super) fn extend(value: &str, width: u32) -> String {
+    let value = value.to_ascii_lowercase();
+    let width = width as usize;
+    if value.len() >= width {
+        return value[value.len() - width..].to_string();
+    }
+    let fill = value
+        .chars()
+        .next()
+        .filter(|c| matches!(c, 'x' | 'z'))
+        .unwrap_or('0');
+    let mut s: String = std::iter::repeat_n(fill, width - value.len()).collect();
+    s += &value;
+    s
+}
+
+/// `$end`までの語を読む
+fn section<'a>(tokens: &mut std::str::SplitWhitespace<'a>) -> Result<Vec<&'a str>, Error> {
+    let mut body = Vec::new();
+    for t in tokens.by_ref() {
+        if t == "$end" {
+            return Ok(body);
+        }
+        body.push(t);
+    }
+    Err(syntax("missing $end"))
+}
+
+fn syntax(msg: &str) -> Error {
+    Error::new(ErrorKind::SyntaxError, msg.to_string())
+}
@@ -0,0 +1,151 @@
+use super::{VarId, VcdWriter};
+use crate::netlist::{mask, NetId, Netlist};
+use crate::sim::Simulator;
+use std::io::{self, Write};
+
+/// 書き出す信号と時刻の絞り込み。空の条件はすべてを通す。
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// この階層(`top`や`top.comb`)の下の信号だけを書く
+    pub scopes: Vec<String>,
+    /// この名前(`x`)か完全な名前(`top.x`)の信号だけを書く
+    pub signals: Vec<String>,
+    /// この時刻の範囲(両端を含む)の変化だけを書く
+    pub window: Option<(u64, u64)>,
+}
+
+impl Filter {
+    fn accepts(&self, scope: &str, name: &str) -> bool {
+        let path = format!("{}.{}", scope, name);
+        let in_scope = self.scopes.is_empty()
+            || self
+                .scopes
+                .iter()
+                .any(|s| scope == s || scope.starts_with(&format!("{}.", s)));
+        let named =
+            self.signals.is_empty() || self.signals.iter().any(|s| *s == name || *s == path);
+        in_scope && named
+    }
+}
+
+/// シミュレーションの各サイクルの値をVCDに記録する。
+///
+/// 時刻の単位は半周期で、`n`サイクル目の立ち上がりが時刻`2n-1`、
+/// 立ち下がりが`2n`になる。`top`には`clk`と入出力、`top.comb`には
+/// 名前の付いた途中の値(`変数名_ネット番号`)を置く。
+pub struct Recorder<'a, W: Write> {
+    nl: &'a Netlist,
+    vcd: VcdWriter<W>,
+    clk: Option<VarId>,
+    signals: Vec<(NetId, VarId)>,
+    last: Vec<u64>,
+    window: (u64, u64),
+    started: bool,
+}
+
+impl<'a, W: Write> Recorder<'a, W> {
+    pub fn new(nl: &'a Netlist, out: W, filter: &Filter) -> io::Result<Self> {
+        let mut vcd = VcdWriter::new(out, "1ns")?;
+        let mut signals = Vec::new();
+        vcd.push_scope("top")?;
+        let clk = match filter.accepts("top", "clk") {
+            true => Some(vcd.add_var("wire", 1, "clk")?),
+            false => None,
+        };
+        let mut ports: Vec<NetId> = nl.inputs().to_vec();
+        ports.extend(nl.outputs().iter().map(|o| o.1));
+        for &(name, n) in nl.outputs() {
+            if filter.accepts("top", name.as_str()) {
+                signals.push((n, vcd.add_var("reg", nl.net(n).width, name.as_str())?));
+            }
+        }
+        for &n in nl.inputs() {
+            let name = nl.net(n).name.map_or("in", |s| s.as_str());
+            if filter.accepts("top", name) {
+                signals.push((n, vcd.add_var("wire", nl.net(n).width, name)?));
+            }
+        }
+        let mut comb = Vec::new();
+        for (id, net) in nl.nets() {
+            if let (Some(name), false) = (net.name, ports.contains(&id)) {
+                let name = format!("{}_{}", name, id.index());
+                if filter.accepts("top.comb", &name) {
+                    comb.push((id, name));
+                }
+            }
+        }
+        if !comb.is_empty() {
+            vcd.push_scope("comb")?;
+            for (id, name) in comb {
+                signals.push((id, vcd.add_var("wire", nl.net(id).width, &name)?));
+            }
+            vcd.pop_scope()?;
+        }
+        vcd.end_definitions()?;
+        Ok(Self {
+            nl,
+            vcd,
+            clk,
+            last: vec![0; signals.len()],
+            signals,
+            window: filter.window.unwrap_or((0, u64::MAX)),
+            started: false,
+        })
+    }
+
+    fn in_window(&self, t: u64) -> bool {
+        self.window.0 <= t && t <= self.window.1
+    }
+
+    /// 時刻`t`の値を書く。窓に入った最初の時刻ではすべての値を書く。
+    fn write(&mut self, t: u64, sim: &dyn Simulator, clk: bool) -> io::Result<()> {
+        let values: Vec<u64> = self
+            .signals
+            .iter()
+            .map(|&(n, _)| sim.value(n) & mask(self.nl.net(n).width))
+            .collect();
+        if self.in_window(t) {
+            self.vcd.time(t);
+            let all = !self.started;
+            if all {
+                self.vcd.begin_dumpvars()?;
+            }
+            if let (Some(c), true) = (self.clk, all || t > 0) {
+                self.vcd.change_u64(c, clk as u64)?;
+            }
+            for (i, &v) in values.iter().enumerate() {
+                if all || v != self.last[i] {
+                    self.vcd.change_u64(self.signals[i].1, v)?;
+                }
+            }
+            if all {
+                self.vcd.end_dumpvars()?;
+            }
+            self.started = true;
+        }
+        self.last = values;
+        Ok(())
+    }
+
+    /// シミュレータの今の値を記録する。始める前と、`step`のたびに呼ぶ。
+    pub fn sample(&mut self, sim: &dyn Simulator) -> io::Result<()> {
+        let cycle = sim.cycle();
+        if cycle == 0 {
+            return self.write(0, sim, false);
+        }
+        self.write(2 * cycle - 1, sim, true)?;
+        // 立ち下がりではクロックだけが変わる
+        let t = 2 * cycle;
+        if self.in_window(t) && self.started {
+            if let Some(c) = self.clk {
+                self.vcd.time(t);
+                self.vcd.change_u64(c, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> W {
+        self.vcd.into_inner()
+    }
+}
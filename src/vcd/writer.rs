@@ -0,0 +1,150 @@
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarId(usize);
+
+/// Value Change Dumpを書く。
+///
+/// `push_scope`と`add_var`で変数を宣言し、`end_definitions`の後は`time`と`change`で
+/// 値の変化を書く。変化のない時刻は書かない。
+/// 値は`0`、`1`、`x`、`z`を上位ビットから並べた文字列で渡す。
+pub struct VcdWriter<W: Write> {
+    out: W,
+    /// 変数の幅と識別コード
+    vars: Vec<(u32, String)>,
+    depth: usize,
+    time: Option<u64>,
+    /// `time`をまだ書いていないか
+    pending: bool,
+}
+
+impl<W: Write> VcdWriter<W> {
+    pub fn new(mut out: W, timescale: &str) -> io::Result<Self> {
+        writeln!(out, "$version compiler $end")?;
+        writeln!(out, "$timescale {} $end", timescale)?;
+        Ok(Self {
+            out,
+            vars: Vec::new(),
+            depth: 0,
+            time: None,
+            pending: false,
+        })
+    }
+
+    pub fn push_scope(&mut self, name: &str) -> io::Result<()> {
+        self.depth += 1;
+        writeln!(self.out, "$scope module {} $end", name)
+    }
+
+    pub fn pop_scope(&mut self) -> io::Result<()> {
+        self.depth -= 1;
+        writeln!(self.out, "$upscope $end")
+    }
+
+    /// `kind`は`wire`や`reg`
+    pub fn add_var(&mut self, kind: &str, width: u32, name: &str) -> io::Result<VarId> {
+        let code = id_code(self.vars.len());
+        if width == 1 {
+            writeln!(self.out, "$var {} 1 {} {} $end", kind, code, name)?;
+        } else {
+            writeln!(
+                self.out,
+                "$var {} {} {} {} [{}:0] $end",
+                kind,
+                width,
+                code,
+                name,
+                width - 1
+            )?;
+        }
+        self.vars.push((width, code));
+        Ok(VarId(self.vars.len() - 1))
+    }
+
+    /// 開いたままのスコープを閉じて宣言を終える
+    pub fn end_definitions(&mut self) -> io::Result<()> {
+        while self.depth > 0 {
+            self.pop_scope()?;
+        }
+        writeln!(self.out, "$enddefinitions $end")
+    }
+
+    /// これからの変化の時刻
+    pub fn time(&mut self, t: u64) {
+        if self.time != Some(t) {
+            self.time = Some(t);
+            self.pending = true;
+        }
+    }
+
+    fn flush_time(&mut self) -> io::Result<()> {
+        if std::mem::take(&mut self.pending) {
+            writeln!(self.out, "#{}", self.time.unwrap_or(0))?;
+        }
+        Ok(())
+    }
+
+    /// すべての変数の値を書く区間を始める
+    pub fn begin_dumpvars(&mut self) -> io::Result<()> {
+        self.flush_time()?;
+        writeln!(self.out, "$dumpvars")
+    }
+
+    pub fn end_dumpvars(&mut self) -> io::Result<()> {
+        writeln!(self.out, "$end")
+    }
+
+    /// 4値の値を書く。短ければ上位を0で埋める。
+    /// 0, 1, x, z以外の文字を含むか空なら`InvalidInput`のエラー。
+    pub fn change(&mut self, var: VarId, bits: &str) -> io::Result<()> {
+        let (width, code) = &self.vars[var.0];
+        let four_state = bits
+            .chars()
+            .all(|c| matches!(c, '0' | '1' | 'x' | 'z' | 'X' | 'Z'));
+        if bits.is_empty() || !four_state {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a four-state value: {:?}", bits),
+            ));
+        }
+        let (width, code) = (*width, code.clone());
+        self.flush_time()?;
+        if width == 1 {
+            let bit = bits.chars().last().unwrap_or('0').to_ascii_lowercase();
+            writeln!(self.out, "{}{}", bit, code)
+        } else {
+            // 読む側は先頭がxかzならその値で、ほかは0で上位を埋めるので、
+            // 省けるのは次が0か1の0だけ。短い値の上位の0も同じ理由で明示する。
+            let mut v = bits.to_ascii_lowercase();
+            if v.len() < width as usize && v.starts_with(['x', 'z']) {
+                v.insert(0, '0');
+            }
+            let mut v = v.as_str();
+            while v.len() > 1 && v.starts_with('0') && matches!(v.as_bytes()[1], b'0' | b'1') {
+                v = &v[1..];
+            }
+            writeln!(self.out, "b{} {}", v, code)
+        }
+    }
+
+    pub fn change_u64(&mut self, var: VarId, value: u64) -> io::Result<()> {
+        self.change(var, &format!("{:b}", value))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// 変数の識別コード。印字できるASCII文字の94進数。
+fn id_code(mut n: usize) -> String {
+    let mut s = String::new();
+    loop {
+        s.push((b'!' + (n % 94) as u8) as char);
+        n /= 94;
+        if n == 0 {
+            return s;
+        }
+        n -= 1;
+    }
+}
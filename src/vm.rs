@@ -0,0 +1,6 @@
+mod bytecode;
+mod compile;
+mod machine;
+
+pub use bytecode::{Code, Op, Reg};
+pub use machine::Vm;
@@ -0,0 +1,143 @@
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+/// レジスタの番号。変数は先頭のレジスタに置かれ、その後ろが一時的な値。
+pub type Reg = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Const {
+        dst: Reg,
+        value: i32,
+    },
+    Move {
+        dst: Reg,
+        src: Reg,
+    },
+    Add {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Sub {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Mul {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    /// 0で割るとエラーで止まる
+    Div {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Neg {
+        dst: Reg,
+        a: Reg,
+    },
+    Eq {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Ne {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Gt {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+    Ge {
+        dst: Reg,
+        a: Reg,
+        b: Reg,
+    },
+}
+
+/// プログラム一回分の命令列
+#[derive(Debug, Clone)]
+pub struct Code {
+    pub(super) ops: Vec<Op>,
+    /// 変数とそのレジスタ。番号は木を評価したときに初めて触る順。
+    pub(super) vars: Vec<Symbol>,
+    pub(super) slots: HashMap<Symbol, Reg>,
+    pub(super) num_regs: usize,
+    /// 各文の命令の終わりと、文の値を置くレジスタ
+    pub(super) stmts: Vec<(usize, Reg)>,
+}
+
+impl Code {
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    pub fn vars(&self) -> &[Symbol] {
+        &self.vars
+    }
+
+    pub fn slot(&self, var: Symbol) -> Option<Reg> {
+        self.slots.get(&var).copied()
+    }
+
+    pub fn num_regs(&self) -> usize {
+        self.num_regs
+    }
+
+    pub fn num_stmts(&self) -> usize {
+        self.stmts.len()
+    }
+
+    /// 文`i`の命令の範囲
+    pub(super) fn stmt_ops(&self, i: usize) -> std::ops::Range<usize> {
+        let start = if i == 0 { 0 } else { self.stmts[i - 1].0 };
+        start..self.stmts[i].0
+    }
+
+    fn reg_name(&self, r: Reg) -> String {
+        match self.vars.get(r as usize) {
+            Some(v) => format!("{}", v),
+            None => format!("r{}", r),
+        }
+    }
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for op in &self.ops {
+            let r = |r: Reg| self.reg_name(r);
+            match *op {
+                Op::Const { dst, value } => writeln!(f, "const {}, {}", r(dst), value)?,
+                Op::Move { dst, src } => writeln!(f, "move  {}, {}", r(dst), r(src))?,
+                Op::Neg { dst, a } => writeln!(f, "neg   {}, {}", r(dst), r(a))?,
+                Op::Add { dst, a, b }
+                | Op::Sub { dst, a, b }
+                | Op::Mul { dst, a, b }
+                | Op::Div { dst, a, b }
+                | Op::Eq { dst, a, b }
+                | Op::Ne { dst, a, b }
+                | Op::Gt { dst, a, b }
+                | Op::Ge { dst, a, b } => {
+                    let name = match op {
+                        Op::Add { .. } => "add",
+                        Op::Sub { .. } => "sub",
+                        Op::Mul { .. } => "mul",
+                        Op::Div { .. } => "div",
+                        Op::Eq { .. } => "eq",
+                        Op::Ne { .. } => "ne",
+                        Op::Gt { .. } => "gt",
+                        _ => "ge",
+                    };
+                    writeln!(f, "{:<5} {}, {}, {}", name, r(dst), r(a), r(b))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+use super::{Code, Op, Reg};
+use crate::ast::{Node, Program, Visitor};
+use crate::datatype::DataUnion;
+use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
+use std::collections::HashMap;
+
+impl Code {
+    /// プログラムを命令列にする。変数は木を評価する順に番号を振る。
+    pub fn compile(prog: &Program) -> Result<Code, Error> {
+        // 変数の番号を先に決めて、一時レジスタをその後ろに置く
+        let mut vars = Vec::new();
+        let mut slots = HashMap::new();
+        prog.visit(&mut VarCollector {
+            vars: &mut vars,
+            slots: &mut slots,
+        });
+        let mut c = Compiler {
+            ops: Vec::new(),
+            slots: &slots,
+            base: vars.len() as Reg,
+            next: vars.len() as Reg,
+            max: vars.len() as Reg,
+            want: None,
+            result: 0,
+            error: None,
+        };
+        let mut stmts = Vec::new();
+        for stmt in prog.stmts() {
+            let value = c.stmt(stmt)?;
+            stmts.push((c.ops.len(), value));
+        }
+        Ok(Code {
+            ops: c.ops,
+            num_regs: c.max as usize,
+            vars,
+            slots,
+            stmts,
+        })
+    }
+}
+
+/// 木を評価するときと同じ順(代入先、値の順)で変数を並べる
+struct VarCollector<'a> {
+    vars: &'a mut Vec<Symbol>,
+    slots: &'a mut HashMap<Symbol, Reg>,
+}
+
+impl<'a> Visitor<'a> for VarCollector<'_> {
+    fn visit_lvar(&mut self, node: Node<'a>) {
+        if let DataUnion::Symbol(s) = node.value() {
+            let vars = &mut *self.vars;
+            self.slots.entry(*s).or_insert_with(|| {
+                vars.push(*s);
+                (vars.len() - 1) as Reg
+            });
+        }
+    }
+}
+
+/// 式の値を置いたレジスタを`result`に残しながら命令を並べる
+struct Compiler<'a> {
+    ops: Vec<Op>,
+    slots: &'a HashMap<Symbol, Reg>,
+    base: Reg,
+    next: Reg,
+    max: Reg,
+    /// 親が値を置いてほしいレジスタ。なければ変数はそのまま、それ以外は一時レジスタに置く。
+    want: Option<Reg>,
+    result: Reg,
+    /// 最初に起きたエラー
+    error: Option<Error>,
+}
+
+impl Compiler<'_> {
+    fn temp(&mut self) -> Reg {
+        let r = self.next;
+        self.next += 1;
+        self.max = self.max.max(self.next);
+        r
+    }
+
+    /// 親が決めたレジスタか、新しい一時レジスタ
+    fn target(&mut self) -> Reg {
+        match self.want.take() {
+            Some(dst) => dst,
+            None => self.temp(),
+        }
+    }
+
+    fn slot(&mut self, node: Node<'_>) -> Reg {
+        match node.value() {
+            DataUnion::Symbol(s) => self.slots[s],
+            _ => {
+                self.fail();
+                0
+            }
+        }
+    }
+
+    fn fail(&mut self) {
+        self.error
+            .get_or_insert_with(|| Error::new(ErrorKind::UnexpectedError, "unexpected error."));
+    }
+
+    /// 文一つを命令にする
+    fn stmt(&mut self, node: Node<'_>) -> Result<Reg, Error> {
+        self.next = self.base;
+        let r = self.operand(node);
+        self.error.take().map_or(Ok(r), Err)
+    }
+
+    /// 子の値があるレジスタ
+    fn operand(&mut self, node: Node<'_>) -> Reg {
+        self.want = None;
+        self.visit_node(node);
+        self.result
+    }
+
+    /// 子を計算してから`op`を置く。子の一時レジスタはその後で空ける。
+    fn op(&mut self, node: Node<'_>, op: fn(Reg, &[Reg]) -> Option<Op>) {
+        let dst = self.target();
+        let mark = self.next;
+        let args: Vec<_> = node.childs().map(|ch| self.operand(ch)).collect();
+        match op(dst, &args) {
+            Some(op) => self.ops.push(op),
+            None => self.fail(),
+        }
+        self.next = mark;
+        self.result = dst;
+    }
+
+    /// 親の決めたレジスタがあれば`src`をそこへ写す
+    fn move_to_want(&mut self, src: Reg) {
+        self.result = match self.want.take() {
+            Some(dst) if dst != src => {
+                self.ops.push(Op::Move { dst, src });
+                dst
+            }
+            _ => src,
+        };
+    }
+}
+
+impl<'a> Visitor<'a> for Compiler<'_> {
+    fn visit_num(&mut self, node: Node<'a>) {
+        let dst = self.target();
+        match node.value() {
+            DataUnion::Num(n) => self.ops.push(Op::Const { dst, value: *n }),
+            _ => self.fail(),
+        }
+        self.result = dst;
+    }
+
+    fn visit_lvar(&mut self, node: Node<'a>) {
+        let src = self.slot(node);
+        self.move_to_want(src);
+    }
+
+    fn visit_assign(&mut self, node: Node<'a>) {
+        let outer = self.want.take();
+        let (Some(lhs), Some(rhs)) = (node.child(0), node.child(1)) else {
+            return self.fail();
+        };
+        let dst = self.slot(lhs);
+        self.want = Some(dst);
+        self.visit_node(rhs);
+        self.want = outer;
+        self.move_to_want(dst);
+    }
+
+    fn visit_neg(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a] => Some(Op::Neg { dst, a }),
+            _ => None,
+        });
+    }
+
+    fn visit_add(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Add { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_sub(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Sub { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_mul(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Mul { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_div(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Div { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_eq(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Eq { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_neq(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Ne { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_greater(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Gt { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    fn visit_greater_eq(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Ge { dst, a, b }),
+            _ => None,
+        });
+    }
+
+    // `a < b`は`b > a`として計算する
+    fn visit_less(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Gt { dst, a: b, b: a }),
+            _ => None,
+        });
+    }
+
+    fn visit_less_eq(&mut self, node: Node<'a>) {
+        self.op(node, |dst, r| match *r {
+            [a, b] => Some(Op::Ge { dst, a: b, b: a }),
+            _ => None,
+        });
+    }
+}
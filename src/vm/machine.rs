@@ -0,0 +1,85 @@
+use super::{Code, Op};
+use crate::error::{Error, ErrorKind};
+use crate::symbol::Symbol;
+
+/// `Code`を実行するレジスタマシン。
+/// レジスタは最初に確保し、変数の値は`run`をまたいで残る。
+#[derive(Debug, Clone)]
+pub struct Vm {
+    code: Code,
+    regs: Vec<i32>,
+}
+
+impl Vm {
+    pub fn new(code: Code) -> Self {
+        let regs = vec![0; code.num_regs];
+        Self { code, regs }
+    }
+
+    pub fn code(&self) -> &Code {
+        &self.code
+    }
+
+    pub fn get(&self, var: Symbol) -> Option<i32> {
+        self.code.slot(var).map(|r| self.regs[r as usize])
+    }
+
+    /// 変数`var`に値を入れる。プログラムに出てこない変数なら`false`。
+    pub fn set(&mut self, var: Symbol, value: i32) -> bool {
+        match self.code.slot(var) {
+            Some(r) => {
+                self.regs[r as usize] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 変数の値。並びは`Code::vars`と同じ。
+    pub fn vars(&self) -> &[i32] {
+        &self.regs[..self.code.vars.len()]
+    }
+
+    /// プログラムを一回実行する
+    pub fn run(&mut self) -> Result<(), Error> {
+        self.exec(0..self.code.ops.len())
+    }
+
+    /// 文`i`だけを実行し、その値を返す
+    pub fn run_stmt(&mut self, i: usize) -> Result<i32, Error> {
+        self.exec(self.code.stmt_ops(i))?;
+        Ok(self.regs[self.code.stmts[i].1 as usize])
+    }
+
+    fn exec(&mut self, ops: std::ops::Range<usize>) -> Result<(), Error> {
+        let r = &mut self.regs;
+        for op in &self.code.ops[ops] {
+            match *op {
+                Op::Const { dst, value } => r[dst as usize] = value,
+                Op::Move { dst, src } => r[dst as usize] = r[src as usize],
+                Op::Add { dst, a, b } => {
+                    r[dst as usize] = r[a as usize].wrapping_add(r[b as usize])
+                }
+                Op::Sub { dst, a, b } => {
+                    r[dst as usize] = r[a as usize].wrapping_sub(r[b as usize])
+                }
+                Op::Mul { dst, a, b } => {
+                    r[dst as usize] = r[a as usize].wrapping_mul(r[b as usize])
+                }
+                Op::Div { dst, a, b } => {
+                    let d = r[b as usize];
+                    if d == 0 {
+                        return Err(Error::new(ErrorKind::DivisionByZero, "division by zero"));
+                    }
+                    r[dst as usize] = r[a as usize].wrapping_div(d)
+                }
+                Op::Neg { dst, a } => r[dst as usize] = r[a as usize].wrapping_neg(),
+                Op::Eq { dst, a, b } => r[dst as usize] = (r[a as usize] == r[b as usize]) as i32,
+                Op::Ne { dst, a, b } => r[dst as usize] = (r[a as usize] != r[b as usize]) as i32,
+                Op::Gt { dst, a, b } => r[dst as usize] = (r[a as usize] > r[b as usize]) as i32,
+                Op::Ge { dst, a, b } => r[dst as usize] = (r[a as usize] >= r[b as usize]) as i32,
+            }
+        }
+        Ok(())
+    }
+}